@@ -1,21 +1,29 @@
 use lasagna::*;
 
-#[derive(Named, Token, Clone, Debug, PartialEq, Eq)]
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
 pub enum JsonToken {
     #[token = "{"]
+    #[token(class = "punctuation")]
     OpenBrace,
     #[token = "}"]
+    #[token(class = "punctuation")]
     CloseBrace,
     #[token = "="]
+    #[token(class = "punctuation")]
     Equal,
     #[token = ","]
+    #[token(class = "punctuation")]
     Comma,
-    #[token]
+    #[token(
+        class = "string",
+        description = "string",
+        sample = "\"a\"",
+        sample = "\"key\""
+    )]
     LitStr(LitStr),
 }
 
-#[derive(Named, Clone, Debug, PartialEq, Eq)]
-#[name = "<string>"]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct LitStr {
     span: Span,
     pub string: String,
@@ -28,7 +36,7 @@ impl Spanned for LitStr {
     }
 }
 
-impl Token for LitStr {
+impl Lex for LitStr {
     fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
         let mut span = lexer.span(0);
 
@@ -65,4 +73,10 @@ pub struct Statement {
     pub equal: Equal,
 }
 
-fn main() {}
+fn main() {
+    let source = r#""key" ="#;
+    let mut parser = SkipWhitespace::new(CharsLexer::new(source, SourcePath::Generated));
+
+    let statement = parser.parse::<Statement>().unwrap();
+    println!("{}", statement.ident.string);
+}
@@ -0,0 +1,71 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigToken {
+    #[token = "="]
+    #[token(class = "operator")]
+    Equal,
+    #[token = ";"]
+    #[token(class = "punctuation")]
+    Semi,
+    #[token(
+        class = "variable",
+        description = "identifier",
+        sample = "name",
+        sample = "x1"
+    )]
+    Ident(Ident),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ident {
+    span: Span,
+    pub ident: String,
+}
+
+impl Spanned for Ident {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Lex for Ident {
+    fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
+        let span = lexer.span(0);
+
+        let mut ident = String::new();
+
+        while let Some(&c) = lexer.peek() {
+            if c.is_alphanumeric() || c == '_' {
+                ident.push(c);
+                lexer.consume();
+            } else {
+                break;
+            }
+        }
+
+        if ident.is_empty() {
+            return Err(Error::spanned(span, "expected identifier"));
+        }
+
+        Ok(Self {
+            span: span | lexer.span(0),
+            ident,
+        })
+    }
+}
+
+#[derive(Spanned, Parse, Debug)]
+#[parse(symbol = "key")]
+pub struct Entry {
+    #[parse(name)]
+    pub key: Ident,
+    pub equal: Equal,
+    pub value: Ident,
+    pub semi: Semi,
+}
+
+fn main() {
+    lsp::Server::<Entry>::new().run_stdio().unwrap();
+}
@@ -4,7 +4,8 @@ use syn::{
     parse::{Parse, ParseStream},
     parse_macro_input, parse_quote,
     spanned::Spanned,
-    Attribute, Data, DeriveInput, Fields, FieldsNamed, FieldsUnnamed, Path, Token, Type,
+    Attribute, Data, DeriveInput, Fields, FieldsNamed, FieldsUnnamed, GenericParam, Generics,
+    Ident, LitStr, Path, Token, Type, Variant,
 };
 
 const NO_FIELDS: &str = "type must have a least one Spanned field";
@@ -24,14 +25,36 @@ impl Parse for Source {
     }
 }
 
+syn::custom_keyword!(symbol);
+
+struct Symbol(LitStr);
+
+impl Parse for Symbol {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        symbol::parse(input)?;
+
+        <Token![=]>::parse(input)?;
+
+        Ok(Self(<LitStr as Parse>::parse(input)?))
+    }
+}
+
+fn is_parse_attr(attr: &Attribute) -> bool {
+    attr.path
+        .get_ident()
+        .map(|ident| ident == "parse")
+        .unwrap_or(false)
+}
+
 #[derive(Clone, Default)]
 struct Attributes {
     token: Option<Type>,
     source: Option<Type>,
+    symbol: Option<LitStr>,
 }
 
 impl Attributes {
-    fn from_attrs(&mut self, attrs: &[Attribute]) {
+    fn read_attrs(&mut self, attrs: &[Attribute]) {
         for attr in attrs {
             if attr
                 .path
@@ -44,15 +67,14 @@ impl Attributes {
                 }
             }
 
-            if attr
-                .path
-                .get_ident()
-                .map(|ident| ident == "parse")
-                .unwrap_or(false)
-            {
+            if is_parse_attr(attr) {
                 if let Ok(Source(ty)) = attr.parse_args::<Source>() {
                     self.source = Some(ty);
                 }
+
+                if let Ok(Symbol(kind)) = attr.parse_args::<Symbol>() {
+                    self.symbol = Some(kind);
+                }
             }
         }
     }
@@ -72,23 +94,84 @@ impl Parse for Peek {
     }
 }
 
+syn::custom_keyword!(deprecated);
+
+/// `deprecated` or `deprecated = "note"`.
+struct Deprecated(Option<LitStr>);
+
+impl Parse for Deprecated {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        deprecated::parse(input)?;
+
+        if input.is_empty() {
+            return Ok(Self(None));
+        }
+
+        <Token![=]>::parse(input)?;
+
+        Ok(Self(Some(<LitStr as Parse>::parse(input)?)))
+    }
+}
+
+impl Deprecated {
+    /// Parses the arguments of a `#[parse(...)]` attribute, `None` if they don't start with
+    /// `deprecated`.
+    fn parse_opt(input: ParseStream) -> syn::Result<Option<Self>> {
+        if !input.peek(deprecated) {
+            input.parse::<TokenStream>()?;
+
+            return Ok(None);
+        }
+
+        input.parse().map(Some)
+    }
+}
+
 #[derive(Default)]
 struct VariantAttributes {
     peek: Option<Path>,
+    symbol: Option<LitStr>,
+    deprecated: Option<Option<LitStr>>,
+    /// A malformed attribute, reported as a compile error.
+    error: Option<syn::Error>,
 }
 
 impl VariantAttributes {
-    fn from_attrs(&mut self, attrs: &[Attribute]) {
+    fn read_attrs(&mut self, attrs: &[Attribute]) {
         for attr in attrs {
-            if attr
-                .path
-                .get_ident()
-                .map(|ident| ident == "parse")
-                .unwrap_or(false)
-            {
+            if is_parse_attr(attr) {
                 if let Ok(path) = attr.parse_args::<Peek>() {
                     self.peek = Some(path.0);
                 }
+
+                if let Ok(Symbol(kind)) = attr.parse_args::<Symbol>() {
+                    self.symbol = Some(kind);
+                }
+
+                if let Ok(args) = attr.parse_args::<TokenStream>() {
+                    match syn::parse::Parser::parse2(Deprecated::parse_opt, args) {
+                        Ok(Some(Deprecated(note))) => self.deprecated = Some(note),
+                        Ok(None) => {}
+                        Err(error) => self.error = Some(error),
+                    }
+                }
+            }
+        }
+    }
+}
+
+syn::custom_keyword!(name);
+
+#[derive(Default)]
+struct FieldAttributes {
+    name: bool,
+}
+
+impl FieldAttributes {
+    fn read_attrs(&mut self, attrs: &[Attribute]) {
+        for attr in attrs {
+            if is_parse_attr(attr) && attr.parse_args::<name>().is_ok() {
+                self.name = true;
             }
         }
     }
@@ -98,12 +181,22 @@ pub fn derive_parse(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let mut attrs = Attributes::default();
-    attrs.from_attrs(&input.attrs);
+    attrs.read_attrs(&input.attrs);
 
     let name = input.ident;
-
-    let mut token = None;
-    let (parse, start) = parse(input.data, attrs.clone(), &mut token);
+    let name_str = name.to_string();
+
+    let mut token = attrs.token.clone();
+    let symbols = symbols(&input.data, &attrs);
+    let dump = dump(&input.data, &name);
+    let print = visit_fields(
+        &input.data,
+        |_, fields, _| quote!(#(::lasagna::Parse::print(#fields, printer);)*),
+    );
+    let generate = generate(&input.data, &name);
+    let grammar = grammar(&input.data, &name, &input.generics);
+    let nullable = nullable(&input.data);
+    let (parse, first) = parse(input.data, &mut token);
 
     let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
 
@@ -111,12 +204,37 @@ pub fn derive_parse(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         impl #impl_generics ::lasagna::Parse for #name #type_generics #where_clause {
             type Token = #token;
 
-            const START: ::lasagna::ParseStart<Self::Token> = #start;
+            #[allow(unused_assignments)]
+            const FIRST: ::lasagna::ParseFirst<Self::Token> = #first;
+
+            const NULLABLE: bool = #nullable;
 
             fn parse(
                 parser: &mut impl ::lasagna::Parser<Self::Token>
             ) -> Result<Self, ::lasagna::Error> {
-                #parse
+                ::lasagna::__trace_parse!(Self, parser, ::lasagna::Parser::nested(parser, |parser| {
+                    #parse
+                }).map_err(|error| error.while_parsing(#name_str)))
+            }
+
+            fn symbols(&self, symbols: &mut ::std::vec::Vec<::lasagna::Symbol>) {
+                #symbols
+            }
+
+            fn dump(&self, dump: &mut ::lasagna::AstDump) {
+                #dump
+            }
+
+            fn print(&self, printer: &mut ::lasagna::Printer) {
+                #print
+            }
+
+            fn generate(generator: &mut ::lasagna::Generator) {
+                #generate
+            }
+
+            fn grammar(grammar: &mut ::lasagna::Grammar) -> ::lasagna::GrammarExpr {
+                #grammar
             }
         }
     };
@@ -124,22 +242,32 @@ pub fn derive_parse(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     proc_macro::TokenStream::from(expanded)
 }
 
-fn parse(data: Data, attrs: Attributes, token: &mut Option<Type>) -> (TokenStream, TokenStream) {
+fn parse(data: Data, token: &mut Option<Type>) -> (TokenStream, TokenStream) {
     match data {
         Data::Enum(data) => {
             let parse = data.variants.iter().map(|variant| {
-                let is_next = variant
-                    .fields
-                    .iter()
-                    .next()
-                    .map(|field| {
-                        let ty = &field.ty;
-
-                        quote!(<#ty as ::lasagna::Parse>::is_next(parser))
-                    })
-                    .expect(NO_FIELDS);
+                let mut variant_attrs = VariantAttributes::default();
+                variant_attrs.read_attrs(&variant.attrs);
+
+                let is_next = if let Some(peek) = variant_attrs.peek {
+                    quote!(#peek(parser))
+                } else if variant.fields.len() == 1 {
+                    let ty = &variant.fields.iter().next().unwrap().ty;
+
+                    quote!(<#ty as ::lasagna::Parse>::is_next(parser))
+                } else {
+                    let first = first(&variant.fields);
+
+                    quote! {{
+                        #[allow(unused_assignments)]
+                        let first = #first;
+
+                        ::lasagna::TokenSet::is_next(&first, parser)
+                    }}
+                };
 
                 let variant_name = &variant.ident;
+                let variant_str = variant_name.to_string();
 
                 let parse_variant = match variant.fields {
                     Fields::Named(ref named) => {
@@ -165,47 +293,71 @@ fn parse(data: Data, attrs: Attributes, token: &mut Option<Type>) -> (TokenStrea
                     Fields::Unit => unimplemented!("{}", NO_FIELDS),
                 };
 
+                let parse_variant = match variant_attrs.deprecated {
+                    Some(note) => {
+                        let msg = format!("use of deprecated '{}'", variant_str);
+                        let hint =
+                            note.map(|note| quote!(.with_hint(::lasagna::ErrorHint::new(#note))));
+
+                        quote! {{
+                            let start = ::lasagna::Parser::span(parser, 0);
+                            let result = #parse_variant;
+
+                            if result.is_ok() {
+                                let span = start | ::lasagna::Parser::prev_span(parser);
+                                let warning = ::lasagna::Error::spanned(span, #msg)
+                                    .with_severity(::lasagna::Severity::Warning)
+                                    #hint;
+
+                                ::lasagna::Parser::diagnostic(parser, warning);
+                            }
+
+                            result
+                        }}
+                    }
+                    None => parse_variant,
+                };
+
+                let error = variant_attrs.error.map(|error| error.to_compile_error());
+
                 quote! {
+                    #error
+
                     match #is_next {
-                        ::std::option::Option::Some(true) => return #parse_variant,
+                        ::std::option::Option::Some(true) => {
+                            ::lasagna::__trace_variant!(Self, #variant_str);
+
+                            return #parse_variant;
+                        }
                         ::std::option::Option::Some(false) => {},
                         _ => {},
                     }
                 }
             });
 
-            let start = data.variants.iter().map(|variant| {
-                variant
-                    .fields
-                    .iter()
-                    .next()
-                    .map(|field| {
-                        let ty = &field.ty;
-
-                        quote!(<#ty as ::lasagna::Parse>::START)
-                    })
-                    .expect(NO_FIELDS)
-            });
+            let first = data.variants.iter().map(|variant| first(&variant.fields));
 
             let parse = quote! {
                 #(#parse)*
 
-                let span = parser.span(0);
+                let (span, found) = match parser.peek()? {
+                    ::std::option::Option::Some(tok) => (
+                        tok.span,
+                        ::lasagna::TokenKind::label(&::lasagna::Token::kind(&tok.token)),
+                    ),
+                    ::std::option::Option::None => {
+                        (parser.span(0), ::std::string::String::from("eof"))
+                    }
+                };
 
-                if let Some(tok) = parser.peek()? {
-                    ::std::result::Result::Err(
-                        ::lasagna::Error::expected_one(span, &Self::START.to_vec(), tok)
-                    )
-                } else {
-                    ::std::result::Result::Err(
-                        ::lasagna::Error::expected_one(span, &Self::START.to_vec(), "eof")
-                    )
-                }
+                ::std::result::Result::Err(
+                    ::lasagna::Parser::expected_error::<Self>(parser, span, found)
+                )
             };
 
-            let start = quote!(&::lasagna::StartTokens::Any(&[#(#start),*]));
+            let first = quote!(::lasagna::TokenSet::EMPTY #(.union(#first))*);
 
-            (parse, start)
+            (parse, first)
         }
         Data::Struct(data) => match data.fields {
             Fields::Named(named) => {
@@ -217,17 +369,7 @@ fn parse(data: Data, attrs: Attributes, token: &mut Option<Type>) -> (TokenStrea
                     })
                 };
 
-                let is_next = named
-                    .named
-                    .first()
-                    .map(|field| {
-                        let ty = &field.ty;
-
-                        quote!(<#ty as ::lasagna::Parse>::START)
-                    })
-                    .expect(NO_FIELDS);
-
-                (parse, is_next)
+                (parse, first(&Fields::Named(named)))
             }
             Fields::Unnamed(unnamed) => {
                 let parse_fields = parse_fields_unnamed(&unnamed, token);
@@ -236,17 +378,7 @@ fn parse(data: Data, attrs: Attributes, token: &mut Option<Type>) -> (TokenStrea
                     Ok(Self(#(#parse_fields),*))
                 };
 
-                let is_next = unnamed
-                    .unnamed
-                    .first()
-                    .map(|field| {
-                        let ty = &field.ty;
-
-                        quote!(<#ty as ::lasagna::Parse>::START)
-                    })
-                    .expect(NO_FIELDS);
-
-                (parse, is_next)
+                (parse, first(&Fields::Unnamed(unnamed)))
             }
             Fields::Unit => unimplemented!("{}", NO_FIELDS),
         },
@@ -254,6 +386,53 @@ fn parse(data: Data, attrs: Attributes, token: &mut Option<Type>) -> (TokenStrea
     }
 }
 
+/// Returns the `FIRST` of `fields`, the union of the `FIRST` of each field up to and
+/// including the first field that isn't nullable.
+fn first(fields: &Fields) -> TokenStream {
+    let tys: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+
+    match &tys[..] {
+        [] => panic!("{}", NO_FIELDS),
+        [ty] => quote!(::lasagna::first_of::<#ty>()),
+        _ => quote! {{
+            let mut first = ::lasagna::TokenSet::EMPTY;
+            let mut nullable = true;
+
+            #(
+                if nullable {
+                    first = first.union(::lasagna::first_of::<#tys>());
+                    nullable = ::lasagna::nullable_of::<#tys>();
+                }
+            )*
+
+            first
+        }},
+    }
+}
+
+/// Returns whether every field in `fields` is nullable.
+fn fields_nullable(fields: &Fields) -> TokenStream {
+    let tys = fields.iter().map(|field| &field.ty);
+
+    quote!(true #(&& ::lasagna::nullable_of::<#tys>())*)
+}
+
+/// Returns the `NULLABLE` of `data`.
+fn nullable(data: &Data) -> TokenStream {
+    match data {
+        Data::Enum(data) => {
+            let variants = data
+                .variants
+                .iter()
+                .map(|variant| fields_nullable(&variant.fields));
+
+            quote!(false #(|| (#variants))*)
+        }
+        Data::Struct(data) => fields_nullable(&data.fields),
+        _ => unimplemented!(),
+    }
+}
+
 fn parse_fields_named<'a>(
     fields: &'a FieldsNamed,
     token: &'a mut Option<Type>,
@@ -288,3 +467,270 @@ fn parse_fields_unnamed<'a>(
         }
     })
 }
+
+/// Returns the fields of `fields` as expressions, and the index of the field marked with
+/// `#[parse(name)]`.
+fn field_exprs(
+    fields: &Fields,
+    access: impl Fn(usize, &Ident) -> TokenStream,
+) -> (Vec<TokenStream>, usize) {
+    let mut name = 0;
+
+    let exprs = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let mut attrs = FieldAttributes::default();
+            attrs.read_attrs(&field.attrs);
+
+            if attrs.name {
+                name = i;
+            }
+
+            let ident = field
+                .ident
+                .clone()
+                .unwrap_or_else(|| Ident::new(&format!("_{}", i), field.span()));
+
+            access(i, &ident)
+        })
+        .collect();
+
+    (exprs, name)
+}
+
+/// Generates the body of `Parse::symbols` for a node with `fields`.
+fn symbols_body(fields: &[TokenStream], name: usize, symbol: Option<&LitStr>) -> TokenStream {
+    match symbol {
+        Some(kind) => {
+            let name = &fields[name];
+
+            quote! {
+                let mut children = ::std::vec::Vec::new();
+                #(::lasagna::Parse::symbols(#fields, &mut children);)*
+
+                symbols.push(
+                    ::lasagna::Symbol::new(
+                        #kind,
+                        ::lasagna::Spanned::span(self),
+                        ::lasagna::Spanned::span(#name),
+                    )
+                    .with_children(children),
+                );
+            }
+        }
+        None => quote! {
+            #(::lasagna::Parse::symbols(#fields, symbols);)*
+        },
+    }
+}
+
+/// Generates a body that runs `body` on the fields of `self`, for enums once per variant.
+///
+/// `body` is passed the variant, the fields as reference expressions and the index of the
+/// field marked with `#[parse(name)]`.
+fn visit_fields(
+    data: &Data,
+    body: impl Fn(Option<&Variant>, &[TokenStream], usize) -> TokenStream,
+) -> TokenStream {
+    match data {
+        Data::Enum(data) => {
+            let variants = data.variants.iter().map(|variant| {
+                let variant_name = &variant.ident;
+                let (fields, name) = field_exprs(&variant.fields, |_, ident| quote!(#ident));
+                let body = body(Some(variant), &fields, name);
+
+                match variant.fields {
+                    Fields::Named(_) => quote! {
+                        Self::#variant_name { #(#fields),* } => { #body }
+                    },
+                    _ => quote! {
+                        Self::#variant_name(#(#fields),*) => { #body }
+                    },
+                }
+            });
+
+            quote! {
+                match self {
+                    #(#variants)*
+                }
+            }
+        }
+        Data::Struct(data) => {
+            let (fields, name) = field_exprs(&data.fields, |i, ident| match data.fields {
+                Fields::Named(_) => quote!(&self.#ident),
+                _ => {
+                    let index = syn::Index::from(i);
+
+                    quote!(&self.#index)
+                }
+            });
+
+            body(None, &fields, name)
+        }
+        _ => unimplemented!(),
+    }
+}
+
+fn symbols(data: &Data, attrs: &Attributes) -> TokenStream {
+    visit_fields(data, |variant, fields, name| {
+        let mut variant_attrs = VariantAttributes::default();
+
+        if let Some(variant) = variant {
+            variant_attrs.read_attrs(&variant.attrs);
+        }
+
+        let symbol = variant_attrs.symbol.as_ref().or(attrs.symbol.as_ref());
+
+        symbols_body(fields, name, symbol)
+    })
+}
+
+fn dump(data: &Data, name: &Ident) -> TokenStream {
+    visit_fields(data, |variant, fields, _| {
+        let (node_name, field_list) = match variant {
+            Some(variant) => (format!("{}::{}", name, variant.ident), &variant.fields),
+            None => match data {
+                Data::Struct(data) => (name.to_string(), &data.fields),
+                _ => unreachable!(),
+            },
+        };
+
+        let labels = field_list.iter().map(|field| match field.ident {
+            Some(ref ident) => {
+                let label = ident.to_string();
+
+                quote!(dump.field(#label);)
+            }
+            None => quote!(),
+        });
+
+        quote! {
+            dump.node(#node_name, |dump| {
+                #(
+                    #labels
+                    ::lasagna::Parse::dump(#fields, dump);
+                )*
+            });
+        }
+    })
+}
+
+fn generate(data: &Data, name: &Ident) -> TokenStream {
+    let fields = |fields: &Fields| {
+        let fields = fields.iter().map(|field| {
+            let ty = &field.ty;
+            let label = field.ident.as_ref().map(|ident| {
+                let label = ident.to_string();
+
+                quote!(generator.field(#label);)
+            });
+
+            quote! {
+                #label
+                <#ty as ::lasagna::Parse>::generate(generator);
+            }
+        });
+
+        quote!(#(#fields)*)
+    };
+
+    match data {
+        Data::Enum(data) => {
+            let count = data.variants.len();
+
+            let variants = data.variants.iter().enumerate().map(|(i, variant)| {
+                let node_name = format!("{}::{}", name, variant.ident);
+                let fields = fields(&variant.fields);
+
+                quote! {
+                    #i => generator.node(#node_name, |generator| {
+                        #fields
+                    }),
+                }
+            });
+
+            quote! {
+                match generator.choose(#count) {
+                    #(#variants)*
+                    _ => ::std::unreachable!(),
+                }
+            }
+        }
+        Data::Struct(data) => {
+            let node_name = name.to_string();
+            let fields = fields(&data.fields);
+
+            quote! {
+                generator.node(#node_name, |generator| {
+                    #fields
+                });
+            }
+        }
+        _ => unimplemented!(),
+    }
+}
+
+fn grammar(data: &Data, name: &Ident, generics: &Generics) -> TokenStream {
+    let sequence = |fields: &Fields| {
+        let tys = fields.iter().map(|field| &field.ty);
+
+        quote! {
+            ::lasagna::GrammarExpr::Sequence(::std::vec![
+                #(<#tys as ::lasagna::Parse>::grammar(grammar)),*
+            ])
+        }
+    };
+
+    let expr = match data {
+        Data::Enum(data) => {
+            let variants = data
+                .variants
+                .iter()
+                .map(|variant| sequence(&variant.fields));
+
+            quote! {
+                ::lasagna::GrammarExpr::Choice(::std::vec![#(#variants),*])
+            }
+        }
+        Data::Struct(data) => sequence(&data.fields),
+        _ => unimplemented!(),
+    };
+
+    let name = rule_name(name, generics);
+
+    quote! {
+        grammar.rule(#name, |grammar| #expr)
+    }
+}
+
+/// Returns the rule name of the type, the name of the type followed by its generic arguments
+/// so that different instantiations are different rules.
+fn rule_name(name: &Ident, generics: &Generics) -> TokenStream {
+    let name = name.to_string();
+    let args = generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => {
+                let ident = &param.ident;
+
+                Some(quote!(::lasagna::short_type_name::<#ident>()))
+            }
+            GenericParam::Const(param) => {
+                let ident = &param.ident;
+
+                Some(quote!(::std::string::ToString::to_string(&#ident)))
+            }
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect::<Vec<_>>();
+
+    if args.is_empty() {
+        return quote!(#name);
+    }
+
+    quote! {
+        ::std::format!("{}<{}>", #name, [#(#args),*].join(", "))
+    }
+}
@@ -3,10 +3,14 @@ use quote::{quote, quote_spanned};
 use syn::{
     parse::{Parse, ParseStream},
     parse_macro_input,
+    punctuated::Punctuated,
     spanned::Spanned,
     Attribute, Data, DataStruct, DeriveInput, Fields, LitStr, Token,
 };
 
+/// Maximum number of variants of a token, the capacity of `TokenSet`.
+const MAX_VARIANTS: usize = 256;
+
 struct MatchString(LitStr);
 
 impl Parse for MatchString {
@@ -17,14 +21,38 @@ impl Parse for MatchString {
     }
 }
 
+/// `key = "value"` argument in `#[token(...)]`.
+struct TokenArg {
+    key: Ident,
+    value: LitStr,
+}
+
+impl Parse for TokenArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = Ident::parse(input)?;
+
+        <Token![=]>::parse(input)?;
+
+        Ok(Self {
+            key,
+            value: <LitStr as Parse>::parse(input)?,
+        })
+    }
+}
+
 #[derive(Default)]
 struct Attributes {
     match_string: Option<LitStr>,
     extern_token: bool,
+    class: Option<LitStr>,
+    description: Option<LitStr>,
+    samples: Vec<LitStr>,
+    /// A malformed attribute, reported as a compile error.
+    error: Option<syn::Error>,
 }
 
 impl Attributes {
-    pub fn from_attrs(&mut self, attrs: &[Attribute]) {
+    pub fn read_attrs(&mut self, attrs: &[Attribute]) {
         for attr in attrs {
             if attr
                 .path
@@ -37,18 +65,53 @@ impl Attributes {
                 {
                     self.match_string = Some(match_string);
                 } else {
+                    if !attr.tokens.is_empty() {
+                        if let Err(error) = self.read_args(attr) {
+                            self.error = Some(error);
+                        }
+                    }
+
                     self.extern_token = true;
                 }
             }
         }
     }
+
+    /// Reads the `key = "value"` arguments of `#[token(...)]`.
+    fn read_args(&mut self, attr: &Attribute) -> syn::Result<()> {
+        let args = attr.parse_args_with(Punctuated::<TokenArg, Token![,]>::parse_terminated)?;
+
+        for arg in args {
+            if arg.key == "class" {
+                self.class = Some(arg.value);
+            } else if arg.key == "description" {
+                self.description = Some(arg.value);
+            } else if arg.key == "sample" {
+                self.samples.push(arg.value);
+            } else {
+                return Err(syn::Error::new_spanned(
+                    &arg.key,
+                    format!(
+                        "unknown token attribute `{}`, expected `class`, `description` or `sample`",
+                        arg.key
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 pub fn derive_token(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let mut attrs = Attributes::default();
-    attrs.from_attrs(&input.attrs);
+    attrs.read_attrs(&input.attrs);
+
+    if let Some(error) = attrs.error {
+        return error.to_compile_error().into();
+    }
 
     let expanded = if attrs.match_string.is_some() {
         match_string_token(input, attrs)
@@ -103,15 +166,15 @@ fn match_string(match_string: &LitStr) -> TokenStream {
                 if next_char != c {
                     return Err(lasagna::Error::expected(
                         lasagna::Lexer::span(lexer, 0),
-                        next_char,
                         c,
+                        next_char,
                     ));
                 }
             } else {
                 return Err(lasagna::Error::expected(
                     lasagna::Lexer::span(lexer, 0),
-                    "<eof>",
                     c,
+                    "<eof>",
                 ));
             }
         }
@@ -124,16 +187,32 @@ fn source_token(input: DeriveInput) -> TokenStream {
 
     match input.data {
         Data::Enum(data) => {
+            if let Some(variant) = data.variants.iter().nth(MAX_VARIANTS) {
+                return syn::Error::new_spanned(
+                    &variant.ident,
+                    format!("tokens can have at most {} variants", MAX_VARIANTS),
+                )
+                .to_compile_error();
+            }
+
             let mut variant_matches = Vec::new();
             let mut lex_variant = Vec::new();
             let mut token_variants = Vec::new();
             let mut display_variants = Vec::new();
             let mut variant_names = Vec::new();
             let mut kind_names = Vec::new();
+            let mut kind_classes = Vec::new();
+            let mut kind_descriptions = Vec::new();
+            let mut kind_literals = Vec::new();
+            let mut classes = Vec::new();
 
             for variant in data.variants {
                 let mut attrs = Attributes::default();
-                attrs.from_attrs(&variant.attrs);
+                attrs.read_attrs(&variant.attrs);
+
+                if let Some(error) = attrs.error {
+                    return error.to_compile_error();
+                }
 
                 let variant_ident = variant.ident;
                 let variant_name = variant_ident.to_string();
@@ -141,6 +220,29 @@ fn source_token(input: DeriveInput) -> TokenStream {
 
                 kind_names.push(variant_ident.clone());
 
+                // literal tokens are described by their text, extern tokens by
+                // `#[token(description = "...")]` or their name
+                match (&attrs.match_string, &attrs.description) {
+                    (Some(string), _) | (None, Some(string)) => {
+                        kind_descriptions.push(quote!(#string))
+                    }
+                    (None, None) => kind_descriptions.push(quote!(#variant_name)),
+                }
+
+                kind_literals.push(attrs.match_string.is_some());
+
+                if let Some(ref class) = attrs.class {
+                    let value = class.value();
+
+                    if !classes.contains(&value) {
+                        classes.push(value);
+                    }
+
+                    kind_classes.push(quote!(::std::option::Option::Some(#class)));
+                } else {
+                    kind_classes.push(quote!(::std::option::Option::None));
+                }
+
                 if let Some(string) = attrs.match_string {
                     variant_matches.push(quote!(Self::#variant_ident));
 
@@ -151,8 +253,6 @@ fn source_token(input: DeriveInput) -> TokenStream {
                     let match_string = match_string(&string);
 
                     lex_variant.push(quote_spanned! {variant_ident.span()=>
-                        let mut fork = lexer.fork();
-
                         {
                             fn variant(
                                 lexer: &mut impl lasagna::Lexer<Output = char>
@@ -162,9 +262,7 @@ fn source_token(input: DeriveInput) -> TokenStream {
                                 Ok(())
                             }
 
-                            if variant(&mut fork).is_ok() {
-                                *lexer = fork;
-
+                            if lasagna::Lexer::attempt(lexer, |lexer| variant(lexer)).is_ok() {
                                 return Ok(Self::#variant_ident);
                             }
                         }
@@ -184,24 +282,46 @@ fn source_token(input: DeriveInput) -> TokenStream {
                         impl lasagna::Parse for #variant_ident {
                             type Token = #name;
 
-                            const START: ::lasagna::ParseStart<Self::Token> =
-                                &::lasagna::StartTokens::Token(&#kind_name::#variant_ident);
+                            const FIRST: ::lasagna::ParseFirst<Self::Token> =
+                                ::lasagna::TokenSet::EMPTY.with(#kind_name::#variant_ident as usize);
 
                             #[inline]
                             fn parse(
                                 parser: &mut impl Parser<Self::Token>,
                             ) -> Result<Self, lasagna::Error> {
-                                let span = parser.span(0);
                                 let token = parser.next()?;
-                                let span = span | parser.span(0);
 
-                                match token {
-                                    #name::#variant_ident => Ok(Self(span)),
+                                match token.token {
+                                    #name::#variant_ident => Ok(Self(token.span)),
                                     _ => ::std::result::Result::Err(
-                                        ::lasagna::Error::expected(span, #variant_name, token),
+                                        ::lasagna::Parser::expected_error::<Self>(
+                                            parser,
+                                            token.span,
+                                            ::lasagna::TokenKind::label(&::lasagna::Token::kind(&token.token)),
+                                        ),
                                     ),
                                 }
                             }
+
+                            #[inline]
+                            fn dump(&self, dump: &mut ::lasagna::AstDump) {
+                                dump.token(#variant_name, self.0);
+                            }
+
+                            #[inline]
+                            fn print(&self, printer: &mut ::lasagna::Printer) {
+                                printer.token(#string);
+                            }
+
+                            #[inline]
+                            fn generate(generator: &mut ::lasagna::Generator) {
+                                generator.token(#variant_name, #string);
+                            }
+
+                            #[inline]
+                            fn grammar(_: &mut ::lasagna::Grammar) -> ::lasagna::GrammarExpr {
+                                ::lasagna::GrammarExpr::Terminal(::std::string::String::from(#string))
+                            }
                         }
                     });
                 } else if attrs.extern_token {
@@ -219,16 +339,31 @@ fn source_token(input: DeriveInput) -> TokenStream {
 
                     let field_ty = &field.ty;
 
+                    let samples = &attrs.samples;
+                    let generate = if samples.is_empty() {
+                        let reason = format!(
+                            "no samples for {}, add #[token(sample = \"...\")]",
+                            variant_name
+                        );
+
+                        quote!(generator.fail(#reason);)
+                    } else {
+                        quote! {
+                            let samples: &[&str] = &[#(#samples),*];
+                            let sample = samples[generator.choose(samples.len())];
+
+                            generator.token(#variant_name, sample);
+                        }
+                    };
+
                     display_variants.push(quote_spanned! {field_ty.span()=>
                         Self::#variant_ident(_) => write!(f, "{}", #variant_name)
                     });
 
                     lex_variant.push(quote_spanned! {variant_ident.span()=>
-                        let mut fork = lexer.fork();
-
-                        if let Ok(tok) = <#field_ty as lasagna::Lex<char>>::lex(&mut fork) {
-                            *lexer = fork;
-
+                        if let Ok(tok) = lasagna::Lexer::attempt(lexer, |lexer| {
+                            <#field_ty as lasagna::Lex<char>>::lex(lexer)
+                        }) {
                             return Ok(Self::#variant_ident(tok));
                         }
                     });
@@ -237,41 +372,97 @@ fn source_token(input: DeriveInput) -> TokenStream {
                         impl lasagna::Parse for #field_ty {
                             type Token = #name;
 
-                            const START: ::lasagna::ParseStart<Self::Token> =
-                                &::lasagna::StartTokens::Token(&#kind_name::#variant_ident);
+                            const FIRST: ::lasagna::ParseFirst<Self::Token> =
+                                ::lasagna::TokenSet::EMPTY.with(#kind_name::#variant_ident as usize);
 
                             #[inline]
                             fn parse(
                                 parser: &mut impl Parser<Self::Token>,
                             ) -> Result<Self, lasagna::Error> {
-                                let span = parser.span(0);
                                 let token = parser.next()?;
-                                let span = span | parser.span(0);
 
-                                match token {
+                                match token.token {
                                     #name::#field_ty(var) => Ok(var),
                                     _ => ::std::result::Result::Err(
-                                        ::lasagna::Error::expected(span, #variant_name, token)
+                                        ::lasagna::Parser::expected_error::<Self>(
+                                            parser,
+                                            token.span,
+                                            ::lasagna::TokenKind::label(&::lasagna::Token::kind(&token.token)),
+                                        ),
                                     ),
                                 }
                             }
+
+                            #[inline]
+                            fn dump(&self, dump: &mut ::lasagna::AstDump) {
+                                dump.token(#variant_name, ::lasagna::Spanned::span(self));
+                            }
+
+                            #[inline]
+                            fn print(&self, printer: &mut ::lasagna::Printer) {
+                                printer.token(&::lasagna::Spanned::span(self).text());
+                            }
+
+                            #[inline]
+                            fn generate(generator: &mut ::lasagna::Generator) {
+                                #generate
+                            }
+
+                            #[inline]
+                            fn grammar(_: &mut ::lasagna::Grammar) -> ::lasagna::GrammarExpr {
+                                ::lasagna::GrammarExpr::Token(::std::string::String::from(#variant_name))
+                            }
                         }
                     });
                 }
             }
 
             quote! {
-                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
                 pub enum #kind_name {
                     #(#kind_names,)*
                 }
 
                 impl ::lasagna::TokenKind for #kind_name {
+                    const ALL: &'static [Self] = &[#(Self::#kind_names),*];
+
+                    const CLASSES: &'static [&'static str] = &[#(#classes),*];
+
                     fn name(&self) -> &str {
                         match self {
                             #(Self::#kind_names => #variant_names,)*
                         }
                     }
+
+                    #[inline]
+                    fn index(&self) -> usize {
+                        *self as usize
+                    }
+
+                    fn description(&self) -> &str {
+                        match self {
+                            #(Self::#kind_names => #kind_descriptions,)*
+                        }
+                    }
+
+                    fn is_literal(&self) -> bool {
+                        match self {
+                            #(Self::#kind_names => #kind_literals,)*
+                        }
+                    }
+
+                    fn class(&self) -> ::std::option::Option<&'static str> {
+                        match self {
+                            #(Self::#kind_names => #kind_classes,)*
+                        }
+                    }
+                }
+
+                impl ::std::fmt::Display for #kind_name {
+                    #[inline]
+                    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                        f.write_str(::lasagna::TokenKind::description(self))
+                    }
                 }
 
                 impl std::fmt::Display for #name {
@@ -306,13 +497,23 @@ fn source_token(input: DeriveInput) -> TokenStream {
                 impl lasagna::Parse for #name {
                     type Token = Self;
 
-                    const START: ::lasagna::ParseStart<Self::Token> = &::lasagna::StartTokens::All;
+                    const FIRST: ::lasagna::ParseFirst<Self::Token> = ::lasagna::TokenSet::FULL;
 
                     #[inline]
                     fn parse(
                         parser: &mut impl Parser<Self::Token>
                     ) -> Result<Self, lasagna::Error> {
-                        parser.next()
+                        parser.next().map(::lasagna::SpannedToken::into_inner)
+                    }
+
+                    #[inline]
+                    fn dump(&self, dump: &mut ::lasagna::AstDump) {
+                        dump.leaf(::lasagna::TokenKind::name(&::lasagna::Token::kind(self)));
+                    }
+
+                    #[inline]
+                    fn grammar(_: &mut ::lasagna::Grammar) -> ::lasagna::GrammarExpr {
+                        ::lasagna::GrammarExpr::Token(::std::string::String::from("token"))
                     }
                 }
 
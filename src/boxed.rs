@@ -0,0 +1,56 @@
+use std::rc::Rc;
+
+use crate::{
+    AstDump, Error, Generator, Grammar, GrammarExpr, Parse, ParseFirst, Parser, Printer, Symbol,
+};
+
+macro_rules! impl_pointer {
+    ($pointer:ident) => {
+        /// Parses `T` behind a pointer, used for recursive nodes.
+        impl<T: Parse> Parse for $pointer<T> {
+            type Token = T::Token;
+
+            const FIRST: ParseFirst<Self::Token> = T::FIRST;
+
+            const NULLABLE: bool = T::NULLABLE;
+
+            #[inline]
+            fn parse(parser: &mut impl Parser<Self::Token>) -> Result<Self, Error> {
+                T::parse(parser).map($pointer::new)
+            }
+
+            #[inline]
+            fn symbols(&self, symbols: &mut Vec<Symbol>) {
+                T::symbols(self, symbols);
+            }
+
+            #[inline]
+            fn dump(&self, dump: &mut AstDump) {
+                T::dump(self, dump);
+            }
+
+            #[inline]
+            fn print(&self, printer: &mut Printer) {
+                T::print(self, printer);
+            }
+
+            #[inline]
+            fn generate(generator: &mut Generator) {
+                T::generate(generator);
+            }
+
+            #[inline]
+            fn grammar(grammar: &mut Grammar) -> GrammarExpr {
+                T::grammar(grammar)
+            }
+
+            #[inline]
+            fn is_next(parser: &mut impl Parser<Self::Token>) -> Option<bool> {
+                T::is_next(parser)
+            }
+        }
+    };
+}
+
+impl_pointer!(Box);
+impl_pointer!(Rc);
@@ -0,0 +1,186 @@
+use crate::{
+    error::expected_msg, AstDump, Error, ErrorHint, Generator, Grammar, GrammarExpr, Parse,
+    ParseFirst, Parser, Printer, Span, Spanned, Symbol, Token, TokenKind,
+};
+
+/// A group of `T` between an open delimiter `O` and a close delimiter `C`.
+///
+/// The span covers both delimiters. A missing close delimiter is reported as an unclosed
+/// delimiter at the open delimiter.
+///
+/// If `T` fails to parse or isn't followed by `C`, the parser skips to the matching close
+/// delimiter with [`Delimited::skip_to_close`] and returns the error, so a caller that recovers
+/// continues after the group. If the group is never closed, the unclosed delimiter is returned
+/// instead and the error is reported as a [diagnostic](Parser::diagnostic).
+///
+/// Derived types that contain a group of themselves need `#[token(...)]`, since the token type
+/// can't be inferred through the cycle.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Delimited<O, T, C> {
+    pub open: O,
+    pub inner: T,
+    pub close: C,
+}
+
+impl<O, T, C> Delimited<O, T, C>
+where
+    O: Parse + Spanned,
+    C: Parse<Token = O::Token>,
+{
+    /// Parses the close delimiter of a group opened at `open`.
+    pub fn parse_close(parser: &mut impl Parser<O::Token>, open: Span) -> Result<C, Error> {
+        match C::is_next(parser) {
+            Some(true) => C::parse(parser),
+            Some(false) => Err(unclosed::<C>(parser, open)),
+            None => parser.attempt(|parser| C::parse(parser)),
+        }
+    }
+
+    /// Skips tokens up to and including the close delimiter matching a group opened at
+    /// `open`, used to recover from an error inside the group.
+    ///
+    /// Nested groups with the same delimiters are skipped as a whole. If `O` and `C` can start
+    /// with the same token, like a group between two `|`, groups can't nest and the next close
+    /// delimiter closes the group.
+    pub fn skip_to_close(parser: &mut impl Parser<O::Token>, open: Span) -> Result<C, Error> {
+        let nests = (O::FIRST & C::FIRST).is_empty();
+        let mut depth = 0;
+
+        loop {
+            if parser.is_empty() {
+                return Err(unclosed::<C>(parser, open));
+            }
+
+            if C::is_next(parser) == Some(true) {
+                if depth == 0 {
+                    return C::parse(parser);
+                }
+
+                depth -= 1;
+            } else if nests && O::is_next(parser) == Some(true) {
+                depth += 1;
+            }
+
+            parser.next()?;
+        }
+    }
+}
+
+impl<O, T, C> Delimited<O, T, C>
+where
+    O: Parse + Spanned,
+    T: Parse<Token = O::Token>,
+    C: Parse<Token = O::Token>,
+{
+    /// Skips to the close delimiter after `error` inside a group opened at `open`, returns
+    /// the error to report.
+    fn recover(parser: &mut impl Parser<O::Token>, open: Span, error: Error) -> Error {
+        match Self::skip_to_close(parser, open) {
+            Ok(_) => error,
+            Err(unclosed) => {
+                parser.diagnostic(error);
+
+                unclosed
+            }
+        }
+    }
+}
+
+/// Returns the unclosed delimiter error for a group opened at `open` and closed by `C`.
+fn unclosed<C: Parse>(parser: &mut impl Parser<C::Token>, open: Span) -> Error {
+    let (span, found) = match parser.peek() {
+        Ok(Some(token)) => (token.span, token.kind().label()),
+        Ok(None) => (parser.span(0), String::from("eof")),
+        Err(err) => return err,
+    };
+
+    parser.add_expected(span, C::FIRST);
+
+    let expected = expected_msg(C::FIRST | parser.expected_at(span), &found);
+    let hint = ErrorHint::spanned(span, format!("{}, found {}", expected, found));
+
+    Error::spanned(open, "unclosed delimiter").with_hint(hint)
+}
+
+impl<O: Spanned, T, C: Spanned> Spanned for Delimited<O, T, C> {
+    #[inline]
+    fn span(&self) -> Span {
+        self.open.span() | self.close.span()
+    }
+}
+
+impl<O, T, C> Parse for Delimited<O, T, C>
+where
+    O: Parse + Spanned,
+    T: Parse<Token = O::Token>,
+    C: Parse<Token = O::Token>,
+{
+    type Token = O::Token;
+
+    const FIRST: ParseFirst<Self::Token> = O::FIRST;
+
+    fn parse(parser: &mut impl Parser<Self::Token>) -> Result<Self, Error> {
+        let open = O::parse(parser)?;
+        let inner = match T::parse(parser) {
+            Ok(inner) => inner,
+            Err(error) => return Err(Self::recover(parser, open.span(), error)),
+        };
+
+        if C::is_next(parser) == Some(false) {
+            if let Some(token) = parser.peek()? {
+                let (span, found) = (token.span, token.kind().label());
+                let error = parser.expected_error::<C>(span, found);
+
+                return Err(Self::recover(parser, open.span(), error));
+            }
+        }
+
+        let close = Self::parse_close(parser, open.span())?;
+
+        Ok(Self { open, inner, close })
+    }
+
+    #[inline]
+    fn symbols(&self, symbols: &mut Vec<Symbol>) {
+        self.inner.symbols(symbols);
+    }
+
+    fn dump(&self, dump: &mut AstDump) {
+        dump.node("Delimited", |dump| {
+            dump.field("open");
+            self.open.dump(dump);
+            dump.field("inner");
+            self.inner.dump(dump);
+            dump.field("close");
+            self.close.dump(dump);
+        });
+    }
+
+    #[inline]
+    fn print(&self, printer: &mut Printer) {
+        self.open.print(printer);
+        self.inner.print(printer);
+        self.close.print(printer);
+    }
+
+    #[inline]
+    fn generate(generator: &mut Generator) {
+        generator.node("Delimited", |generator| {
+            generator.field("open");
+            O::generate(generator);
+            generator.field("inner");
+            T::generate(generator);
+            generator.field("close");
+            C::generate(generator);
+        });
+    }
+
+    #[inline]
+    fn grammar(grammar: &mut Grammar) -> GrammarExpr {
+        GrammarExpr::Sequence(vec![
+            O::grammar(grammar),
+            T::grammar(grammar),
+            C::grammar(grammar),
+        ])
+    }
+}
@@ -0,0 +1,168 @@
+use std::{borrow::Cow, fmt::Write};
+
+use crate::{Parse, Span};
+
+#[derive(Clone, Debug)]
+struct Line {
+    depth: usize,
+    label: Option<&'static str>,
+    name: String,
+    text: Option<Cow<'static, str>>,
+    span: Option<Span>,
+}
+
+/// Writer for indented dumps of syntax trees, see [`Parse::dump`].
+///
+/// Each line holds the field name, node type, token text and span of a node. The span of a
+/// node is the union of the spans of its tokens. The output is stable, which makes it
+/// suitable for golden-file tests.
+#[derive(Clone, Debug)]
+pub struct AstDump {
+    lines: Vec<Line>,
+    depth: usize,
+    label: Option<&'static str>,
+    spans: bool,
+}
+
+impl Default for AstDump {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AstDump {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            lines: Vec::new(),
+            depth: 0,
+            label: None,
+            spans: true,
+        }
+    }
+
+    /// Creates a dump that omits spans, used to compare trees parsed from different sources.
+    #[inline]
+    pub fn without_spans() -> Self {
+        Self {
+            spans: false,
+            ..Self::new()
+        }
+    }
+
+    /// Sets the field name of the next node.
+    #[inline]
+    pub fn field(&mut self, label: &'static str) {
+        self.label = Some(label);
+    }
+
+    /// Writes a node, `f` writes the children of the node.
+    pub fn node(&mut self, name: impl Into<String>, f: impl FnOnce(&mut Self)) {
+        let index = self.open(name.into());
+        f(self);
+        self.close(index);
+    }
+
+    /// Starts a node and returns its index, children are written until [`AstDump::close`].
+    pub(crate) fn open(&mut self, name: String) -> usize {
+        let index = self.push(name, None, None);
+        self.depth += 1;
+        index
+    }
+
+    /// Ends the node at `index` started with [`AstDump::open`].
+    pub(crate) fn close(&mut self, index: usize) {
+        self.depth -= 1;
+
+        let span = self.lines[index + 1..]
+            .iter()
+            .filter(|line| line.depth == self.depth + 1)
+            .filter_map(|line| line.span)
+            .reduce(|a, b| a | b);
+
+        self.lines[index].span = span;
+    }
+
+    /// Writes a token with its text.
+    #[inline]
+    pub fn token(&mut self, name: impl Into<String>, span: Span) {
+        self.push(name.into(), Some(span.text()), Some(span));
+    }
+
+    /// Writes a token with text but without a span, used for generated tokens.
+    #[inline]
+    pub(crate) fn token_text(&mut self, name: impl Into<String>, text: &str) {
+        self.push(name.into(), Some(Cow::Owned(text.to_owned())), None);
+    }
+
+    /// Writes a node without children or span.
+    #[inline]
+    pub fn leaf(&mut self, name: impl Into<String>) {
+        self.push(name.into(), None, None);
+    }
+
+    fn push(&mut self, name: String, text: Option<Cow<'static, str>>, span: Option<Span>) -> usize {
+        self.lines.push(Line {
+            depth: self.depth,
+            label: self.label.take(),
+            name,
+            text,
+            span,
+        });
+
+        self.lines.len() - 1
+    }
+
+    /// Iterates over the spans of every written token and node.
+    #[cfg(feature = "fuzz")]
+    #[inline]
+    pub(crate) fn spans(&self) -> impl Iterator<Item = Span> + '_ {
+        self.lines.iter().filter_map(|line| line.span)
+    }
+
+    pub fn finish(self) -> String {
+        let mut out = String::new();
+
+        for line in &self.lines {
+            for _ in 0..line.depth {
+                out.push_str("  ");
+            }
+
+            if let Some(label) = line.label {
+                let _ = write!(out, "{}: ", label);
+            }
+
+            out.push_str(&line.name);
+
+            if let Some(ref text) = line.text {
+                let _ = write!(out, " {:?}", text);
+            }
+
+            if let (true, Some(span)) = (self.spans, line.span) {
+                let (end_line, end_column) = span.end();
+
+                let _ = write!(
+                    out,
+                    " @ {}:{}..{}:{}",
+                    span.line + 1,
+                    span.column + 1,
+                    end_line + 1,
+                    end_column + 1
+                );
+            }
+
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+/// Returns the dump of `node`, see [`AstDump`].
+#[inline]
+pub fn dump_ast(node: &impl Parse) -> String {
+    let mut dump = AstDump::new();
+    node.dump(&mut dump);
+    dump.finish()
+}
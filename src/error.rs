@@ -1,72 +1,159 @@
 use std::{
     fmt::{Debug, Display},
+    io,
+    num::{ParseFloatError, ParseIntError},
     panic::Location,
+    sync::Arc,
 };
 
-use crate::Span;
+use crate::{Span, TokenKind, TokenSet};
 
-#[derive(Debug)]
-pub struct Error {
+#[derive(Clone, Debug)]
+struct ErrorInner {
     message: String,
-    source: Option<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    source: Option<Arc<dyn std::error::Error + Send + Sync + 'static>>,
     span: Option<Span>,
     hints: Vec<ErrorHint>,
+    suggestions: Vec<Suggestion>,
+    node: Option<String>,
+    code: Option<String>,
+    severity: Severity,
     location: &'static Location<'static>,
 }
 
+/// Error produced while lexing or parsing.
+///
+/// The contents are boxed to keep `Result<T, Error>` small.
+#[derive(Clone, Debug)]
+pub struct Error {
+    inner: Box<ErrorInner>,
+}
+
 impl Error {
     #[track_caller]
     pub fn new(msg: impl Display) -> Self {
         Self {
-            message: msg.to_string(),
-            source: None,
-            span: None,
-            hints: Vec::new(),
-            location: Location::caller(),
+            inner: Box::new(ErrorInner {
+                message: msg.to_string(),
+                source: None,
+                span: None,
+                hints: Vec::new(),
+                suggestions: Vec::new(),
+                node: None,
+                code: None,
+                severity: Severity::Error,
+                location: Location::caller(),
+            }),
         }
     }
 
     #[track_caller]
     pub fn spanned(span: Span, msg: impl Display) -> Self {
-        Self {
-            message: msg.to_string(),
-            source: None,
-            span: Some(span),
-            hints: Vec::new(),
-            location: Location::caller(),
-        }
+        let mut error = Self::new(msg);
+        error.inner.span = Some(span);
+        error
     }
 
     pub fn msg(&self) -> &str {
-        &self.message
+        &self.inner.message
     }
 
-    pub fn source(&self) -> Option<&Box<dyn std::error::Error + Send + Sync + 'static>> {
-        self.source.as_ref()
+    /// Returns the error that caused `self`, set with [`Error::with_source`].
+    ///
+    /// This is [`std::error::Error::source`], but keeps the `Send + Sync` bounds.
+    pub fn source_ref(&self) -> Option<&(dyn std::error::Error + Send + Sync + 'static)> {
+        self.inner.source.as_deref()
     }
 
     pub fn span(&self) -> Option<Span> {
-        self.span
+        self.inner.span
     }
 
     pub fn hints(&self) -> &[ErrorHint] {
-        &self.hints
+        &self.inner.hints
+    }
+
+    /// Returns the fixes suggested for the error, see [`Suggestion`].
+    pub fn suggestions(&self) -> &[Suggestion] {
+        &self.inner.suggestions
+    }
+
+    /// Returns the error code, like `E0001`, set with [`Error::with_code`].
+    pub fn code(&self) -> Option<&str> {
+        self.inner.code.as_deref()
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.inner.severity
+    }
+
+    /// Returns the innermost node being parsed when the error occurred, see
+    /// [`Error::while_parsing`].
+    pub fn node(&self) -> Option<&str> {
+        self.inner.node.as_deref()
     }
 
     pub fn location(&self) -> &'static Location<'static> {
-        self.location
+        self.inner.location
     }
 
+    /// Creates an error at `span`, the span of the token that was found.
     #[track_caller]
     pub fn expected(span: Span, expected: impl Display, found: impl Display) -> Self {
-        Self::new(format!("expected '{}'", expected))
+        Self::spanned(span, format!("expected '{}'", expected))
             .with_hint(ErrorHint::spanned(span, format!("found '{}'", found)))
     }
 
+    /// Creates an error at `span` expecting one of `expected`, `found` is the label of the
+    /// token that was found, see [`TokenKind::label`].
+    ///
+    /// A full set expects nothing in particular, the error then only reports `found`.
     #[track_caller]
-    pub fn expected_one(span: Span, expected: &[impl Debug], found: impl Display) -> Self {
-        Self::new(format!("expected '{:?}'", expected))
-            .with_hint(ErrorHint::spanned(span, format!("found '{}'", found)))
+    pub fn expected_one<K: TokenKind>(
+        span: Span,
+        expected: TokenSet<K>,
+        found: impl Display,
+    ) -> Self {
+        let msg = expected_msg(expected, &found);
+
+        Self::spanned(span, msg).with_hint(ErrorHint::spanned(span, format!("found {}", found)))
+    }
+
+    /// Records that the error occurred while parsing `node`, only the innermost node is kept.
+    pub fn while_parsing(mut self, node: impl Display) -> Self {
+        if self.inner.node.is_none() {
+            self.inner.node = Some(node.to_string());
+        }
+
+        self
+    }
+
+    /// Sets the error that caused `self`, reported by [`std::error::Error::source`].
+    pub fn with_source(
+        mut self,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    ) -> Self {
+        self.inner.source = Some(Arc::from(source.into()));
+        self
+    }
+
+    pub fn with_code(mut self, code: impl Display) -> Self {
+        self.inner.code = Some(code.to_string());
+        self
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.inner.severity = severity;
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.add_suggestion(suggestion);
+        self
+    }
+
+    pub fn add_suggestion(&mut self, suggestion: Suggestion) {
+        self.inner.suggestions.push(suggestion);
     }
 
     pub fn with_hint(mut self, hint: ErrorHint) -> Self {
@@ -75,14 +162,100 @@ impl Error {
     }
 
     pub fn add_hint(&mut self, hint: ErrorHint) {
-        self.hints.push(hint);
+        self.inner.hints.push(hint);
+    }
+}
+
+/// Severity of an [`Error`], ordered from most to least severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl Severity {
+    /// Returns the name of the severity, as used by rustc.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Note => "note",
+            Self::Help => "help",
+        }
+    }
+
+    /// Returns the LSP `DiagnosticSeverity`.
+    #[inline]
+    pub fn lsp(&self) -> u8 {
+        match self {
+            Self::Error => 1,
+            Self::Warning => 2,
+            Self::Note => 3,
+            Self::Help => 4,
+        }
+    }
+}
+
+impl Display for Severity {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A suggested fix for an [`Error`], replacing the text at `span` with `replacement`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suggestion {
+    pub msg: String,
+    pub span: Span,
+    pub replacement: String,
+}
+
+impl Suggestion {
+    #[inline]
+    pub fn new(span: Span, replacement: impl Into<String>, msg: impl Display) -> Self {
+        Self {
+            msg: msg.to_string(),
+            span,
+            replacement: replacement.into(),
+        }
+    }
+
+    /// Suggests inserting `text` at the start of `span`.
+    #[inline]
+    pub fn insert(span: Span, text: impl Into<String>, msg: impl Display) -> Self {
+        Self::new(Span { length: 0, ..span }, text, msg)
+    }
+
+    /// Suggests removing the text at `span`.
+    #[inline]
+    pub fn remove(span: Span, msg: impl Display) -> Self {
+        Self::new(span, "", msg)
+    }
+}
+
+/// Returns "expected one of ..." for `expected`, or "unexpected `found`" if it is full.
+pub(crate) fn expected_msg<K: TokenKind>(expected: TokenSet<K>, found: impl Display) -> String {
+    let labels = if expected == TokenSet::FULL {
+        Vec::new()
+    } else {
+        expected.iter().map(|kind| kind.label()).collect()
+    };
+
+    match labels[..] {
+        [] => format!("unexpected {}", found),
+        [ref label] => format!("expected {}", label),
+        _ => format!("expected one of {}", labels.join(", ")),
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ErrorHint {
     msg: String,
-    source: Option<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    source: Option<Arc<dyn std::error::Error + Send + Sync + 'static>>,
     spans: Vec<Span>,
 }
 
@@ -103,12 +276,24 @@ impl ErrorHint {
         }
     }
 
+    /// Sets the error that caused the hint, reported by [`std::error::Error::source`].
+    pub fn with_source(
+        mut self,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    ) -> Self {
+        self.source = Some(Arc::from(source.into()));
+        self
+    }
+
     pub fn msg(&self) -> &str {
         &self.msg
     }
 
-    pub fn source(&self) -> Option<&Box<dyn std::error::Error + Send + Sync + 'static>> {
-        self.source.as_ref()
+    /// Returns the error that caused the hint, set with [`ErrorHint::with_source`].
+    ///
+    /// This is [`std::error::Error::source`], but keeps the `Send + Sync` bounds.
+    pub fn source_ref(&self) -> Option<&(dyn std::error::Error + Send + Sync + 'static)> {
+        self.source.as_deref()
     }
 
     pub fn span(&self) -> Option<Span> {
@@ -123,14 +308,278 @@ impl ErrorHint {
 impl std::fmt::Display for Error {
     #[inline]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // plain errors keep the bare message
+        match (self.severity(), self.code()) {
+            (Severity::Error, None) => {}
+            (severity, None) => write!(f, "{}: ", severity)?,
+            (severity, Some(code)) => write!(f, "{}[{}]: ", severity, code)?,
+        }
+
         writeln!(f, "{}", self.msg())?;
 
+        if let Some(span) = self.span() {
+            write_location(f, span)?;
+        }
+
+        if let Some(node) = self.node() {
+            writeln!(f, "while parsing {}", node)?;
+        }
+
+        if let Some(source) = self.source_ref() {
+            write_sources(f, source)?;
+        }
+
         for hint in self.hints() {
             writeln!(f, "{}", hint.msg())?;
+
+            if let Some(source) = hint.source_ref() {
+                write_sources(f, source)?;
+            }
+
+            for &span in hint.spans() {
+                if Some(span) != self.span() {
+                    write_location(f, span)?;
+                }
+            }
+        }
+
+        for suggestion in self.suggestions() {
+            match suggestion.replacement.as_str() {
+                "" => writeln!(f, "help: {}", suggestion.msg)?,
+                replacement => writeln!(f, "help: {}: `{}`", suggestion.msg, replacement)?,
+            }
+
+            if Some(suggestion.span) != self.span() {
+                write_location(f, suggestion.span)?;
+            }
         }
 
         Ok(())
     }
 }
 
-impl std::error::Error for Error {}
+/// Writes `source` and the errors that caused it.
+fn write_sources(
+    f: &mut std::fmt::Formatter<'_>,
+    source: &(dyn std::error::Error + 'static),
+) -> std::fmt::Result {
+    let mut source = Some(source);
+
+    while let Some(error) = source {
+        // an `Error` writes its own sources
+        if error.is::<Error>() {
+            return write!(f, "caused by: {}", error);
+        }
+
+        writeln!(f, "caused by: {}", error)?;
+        source = error.source();
+    }
+
+    Ok(())
+}
+
+/// Writes the file, line and column of `span`, lines and columns are displayed one-based.
+fn write_location(f: &mut std::fmt::Formatter<'_>, span: Span) -> std::fmt::Result {
+    writeln!(f, "  --> {}", SpanLocation(span))?;
+
+    for expansion in span.expansions() {
+        writeln!(f, "  = in expansion of {}", expansion.name)?;
+        writeln!(f, "  --> {}", SpanLocation(expansion.call_site))?;
+    }
+
+    Ok(())
+}
+
+/// Displays the path, line and column of a span.
+struct SpanLocation(Span);
+
+impl Display for SpanLocation {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.0.path,
+            self.0.line + 1,
+            self.0.column + 1
+        )
+    }
+}
+
+impl std::error::Error for Error {
+    /// Returns the source set with [`Error::with_source`].
+    ///
+    /// Hints explain an error rather than cause it, so their sources aren't part of the chain.
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self.inner.source {
+            Some(ref source) => Some(source.as_ref()),
+            None => None,
+        }
+    }
+}
+
+impl Display for ErrorHint {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.msg())
+    }
+}
+
+impl std::error::Error for ErrorHint {
+    #[inline]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self.source {
+            Some(ref source) => Some(source.as_ref()),
+            None => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    #[track_caller]
+    fn from(error: io::Error) -> Self {
+        Self::new("i/o error").with_source(error)
+    }
+}
+
+impl From<ParseIntError> for Error {
+    #[track_caller]
+    fn from(error: ParseIntError) -> Self {
+        Self::new("invalid integer").with_source(error)
+    }
+}
+
+impl From<ParseFloatError> for Error {
+    #[track_caller]
+    fn from(error: ParseFloatError) -> Self {
+        Self::new("invalid float").with_source(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharsLexer, Lexer, SourcePath};
+
+    #[test]
+    fn severities() {
+        let severities = [
+            Severity::Error,
+            Severity::Warning,
+            Severity::Note,
+            Severity::Help,
+        ];
+
+        let names = severities
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let lsp = severities.iter().map(Severity::lsp).collect::<Vec<_>>();
+
+        assert_eq!(names, ["error", "warning", "note", "help"]);
+        assert_eq!(lsp, [1, 2, 3, 4]);
+        assert!(Severity::Error < Severity::Warning);
+    }
+
+    #[test]
+    fn display_prefixes_severity_and_code() {
+        assert_eq!(Error::new("bad").to_string(), "bad\n");
+        assert_eq!(
+            Error::new("bad")
+                .with_severity(Severity::Warning)
+                .to_string(),
+            "warning: bad\n"
+        );
+        assert_eq!(
+            Error::new("bad").with_code("E0001").to_string(),
+            "error[E0001]: bad\n"
+        );
+    }
+
+    #[test]
+    fn with_source_is_the_std_source() {
+        let error = Error::new("outer").with_source(Error::new("inner"));
+        let source = std::error::Error::source(&error).unwrap();
+
+        assert_eq!(source.downcast_ref::<Error>().unwrap().msg(), "inner");
+        assert_eq!(error.source_ref().unwrap().to_string(), "inner\n");
+    }
+
+    #[test]
+    fn from_common_errors() {
+        let error = Error::from("x".parse::<u32>().unwrap_err());
+
+        assert_eq!(error.msg(), "invalid integer");
+        assert!(std::error::Error::source(&error)
+            .unwrap()
+            .is::<ParseIntError>());
+
+        let error = Error::from("x".parse::<f64>().unwrap_err());
+
+        assert_eq!(error.msg(), "invalid float");
+        assert!(error.source_ref().unwrap().is::<ParseFloatError>());
+
+        let error = Error::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+
+        assert_eq!(error.msg(), "i/o error");
+        assert_eq!(error.source_ref().unwrap().to_string(), "missing");
+    }
+
+    #[test]
+    fn hint_sources_are_not_causes() {
+        let hint = ErrorHint::new("while reading the input").with_source(Error::new("inner"));
+        let error = Error::new("outer").with_hint(hint);
+
+        assert!(std::error::Error::source(&error).is_none());
+        assert!(error.hints()[0].source_ref().is_some());
+        assert!(std::error::Error::source(&error.hints()[0]).is_some());
+    }
+
+    #[test]
+    fn chain_rendering() {
+        let io = io::Error::new(io::ErrorKind::NotFound, "missing");
+        let error = Error::new("outer")
+            .with_source(Error::new("middle").with_source(io))
+            .with_hint(ErrorHint::new("a hint").with_source(Error::new("hint cause")));
+
+        assert_eq!(
+            error.to_string(),
+            concat!(
+                "outer\n",
+                "caused by: middle\n",
+                "caused by: missing\n",
+                "a hint\n",
+                "caused by: hint cause\n",
+            )
+        );
+    }
+
+    #[test]
+    fn expansion_locations() {
+        let mut lexer = CharsLexer::new_static("a\n  outer!()", SourcePath::Other("main.rs"));
+        lexer.consume();
+        lexer.consume();
+        lexer.consume();
+        lexer.consume();
+        let main = lexer.span(8);
+
+        let outer =
+            CharsLexer::new_static("inner!()", SourcePath::expansion("outer!", main)).span(8);
+
+        let mut lexer = CharsLexer::new_static(" x", SourcePath::expansion("inner!", outer));
+        lexer.consume();
+        let inner = lexer.span(1);
+
+        assert_eq!(
+            Error::spanned(inner, "bad").to_string(),
+            concat!(
+                "bad\n",
+                "  --> <expansion of inner!>:1:2\n",
+                "  = in expansion of inner!\n",
+                "  --> <expansion of outer!>:1:1\n",
+                "  = in expansion of outer!\n",
+                "  --> main.rs:2:3\n",
+            )
+        );
+    }
+}
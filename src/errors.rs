@@ -0,0 +1,308 @@
+use std::fmt::Display;
+
+use crate::{Error, Severity, SourcePath};
+
+/// Default number of errors displayed by [`Errors`].
+pub const DEFAULT_ERROR_LIMIT: usize = 32;
+
+/// A collection of [`Error`]s, kept sorted by position.
+///
+/// Errors with the same span and message are reported once, the most severe one is kept.
+/// Errors with different messages at the same span are all kept, so a warning doesn't hide an
+/// error after it. Errors without a span are sorted after all others and deduplicated by
+/// message. Displaying the collection shows at most [`Errors::limit`] errors, followed by the
+/// number of diagnostics left out.
+#[derive(Clone, Debug)]
+pub struct Errors {
+    errors: Vec<Error>,
+    limit: usize,
+}
+
+impl Default for Errors {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Errors {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            errors: Vec::new(),
+            limit: DEFAULT_ERROR_LIMIT,
+        }
+    }
+
+    /// Sets the maximum number of errors displayed.
+    #[inline]
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Adds `error`, replacing an error with the same span and message if `error` is more
+    /// severe.
+    ///
+    /// If that error is as or more severe, `error` is dropped.
+    pub fn push(&mut self, error: Error) {
+        let key = key(&error);
+
+        match self
+            .errors
+            .binary_search_by(|other| self::key(other).cmp(&key))
+        {
+            Ok(index) => {
+                if error.severity() < self.errors[index].severity() {
+                    self.errors[index] = error;
+                }
+            }
+            Err(index) => self.errors.insert(index, error),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns whether any error has [`Severity::Error`], warnings and notes alone don't fail.
+    #[inline]
+    pub fn has_errors(&self) -> bool {
+        self.errors
+            .iter()
+            .any(|error| error.severity() == Severity::Error)
+    }
+
+    /// Iterates over the errors, in order of position.
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, Error> {
+        self.errors.iter()
+    }
+
+    #[inline]
+    pub fn into_vec(self) -> Vec<Error> {
+        self.errors
+    }
+
+    /// Returns `Ok(value)` if there are no errors with [`Severity::Error`], otherwise `self`.
+    #[inline]
+    pub fn into_result<T>(self, value: T) -> Result<T, Self> {
+        if self.has_errors() {
+            Err(self)
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+/// Position and message of an error, errors without a span sort last by message.
+fn key(error: &Error) -> Result<(SourcePath, usize, usize, &str), &str> {
+    match error.span() {
+        Some(span) => {
+            let span = span.origin();
+
+            Ok((span.path, span.offset, span.length, error.msg()))
+        }
+        None => Err(error.msg()),
+    }
+}
+
+impl From<Error> for Errors {
+    #[inline]
+    fn from(error: Error) -> Self {
+        let mut errors = Self::new();
+        errors.push(error);
+        errors
+    }
+}
+
+impl Extend<Error> for Errors {
+    #[inline]
+    fn extend<I: IntoIterator<Item = Error>>(&mut self, iter: I) {
+        for error in iter {
+            self.push(error);
+        }
+    }
+}
+
+impl std::iter::FromIterator<Error> for Errors {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = Error>>(iter: I) -> Self {
+        let mut errors = Self::new();
+        errors.extend(iter);
+        errors
+    }
+}
+
+impl IntoIterator for Errors {
+    type Item = Error;
+    type IntoIter = std::vec::IntoIter<Error>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Errors {
+    type Item = &'a Error;
+    type IntoIter = std::slice::Iter<'a, Error>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.iter()
+    }
+}
+
+impl Display for Errors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, error) in self.errors.iter().take(self.limit).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{}", error)?;
+        }
+
+        if self.errors.len() > self.limit {
+            let more = self.errors.len() - self.limit;
+
+            if self.limit > 0 {
+                writeln!(f)?;
+            }
+
+            match more {
+                1 => writeln!(f, "and 1 more diagnostic")?,
+                _ => writeln!(f, "and {} more diagnostics", more)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The errors are siblings rather than causes of each other, so there is no source.
+impl std::error::Error for Errors {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharsLexer, Lexer, Span};
+
+    /// Returns spans of length one at the offsets `0..count`.
+    fn spans(count: usize) -> Vec<Span> {
+        let mut lexer = CharsLexer::new("abcdef", SourcePath::Generated);
+
+        (0..count)
+            .map(|_| {
+                let span = lexer.span(1);
+                lexer.consume();
+                span
+            })
+            .collect()
+    }
+
+    fn msgs(errors: &Errors) -> Vec<&str> {
+        errors.iter().map(Error::msg).collect()
+    }
+
+    #[test]
+    fn sorted_by_position_unspanned_last() {
+        let spans = spans(3);
+        let errors = vec![
+            Error::new("b"),
+            Error::spanned(spans[2], "third"),
+            Error::new("a"),
+            Error::spanned(spans[0], "first"),
+            Error::spanned(spans[1], "second"),
+        ]
+        .into_iter()
+        .collect::<Errors>();
+
+        assert_eq!(msgs(&errors), ["first", "second", "third", "a", "b"]);
+    }
+
+    #[test]
+    fn identical_errors_are_reported_once() {
+        let spans = spans(2);
+        let mut errors = Errors::new();
+        errors.push(Error::spanned(spans[0], "first"));
+        errors.push(Error::spanned(spans[0], "first"));
+        errors.push(Error::spanned(spans[1], "first"));
+        errors.push(Error::new("unspanned"));
+        errors.push(Error::new("unspanned"));
+
+        assert_eq!(msgs(&errors), ["first", "first", "unspanned"]);
+    }
+
+    #[test]
+    fn different_messages_at_one_span_are_kept() {
+        let spans = spans(1);
+        let warning = Error::spanned(spans[0], "unused").with_severity(Severity::Warning);
+        let errors = vec![
+            warning.clone(),
+            Error::spanned(spans[0], "expected ';'"),
+            warning,
+        ]
+        .into_iter()
+        .collect::<Errors>();
+
+        assert_eq!(msgs(&errors), ["expected ';'", "unused"]);
+        assert!(errors.has_errors());
+    }
+
+    #[test]
+    fn more_severe_errors_replace_less_severe() {
+        let spans = spans(1);
+        let warning = || Error::spanned(spans[0], "message").with_severity(Severity::Warning);
+        let error = || Error::spanned(spans[0], "message");
+
+        let errors = vec![warning(), error()].into_iter().collect::<Errors>();
+        assert_eq!(errors.len(), 1);
+        assert!(errors.has_errors());
+
+        let errors = vec![error(), warning()].into_iter().collect::<Errors>();
+        assert_eq!(errors.len(), 1);
+        assert!(errors.has_errors());
+
+        let errors = Errors::from(warning());
+        assert!(!errors.has_errors());
+        assert!(errors.into_result(()).is_ok());
+    }
+
+    #[test]
+    fn display_limit() {
+        let errors = vec![Error::new("a"), Error::new("b"), Error::new("c")]
+            .into_iter()
+            .collect::<Errors>();
+
+        assert_eq!(errors.to_string(), "a\n\nb\n\nc\n");
+        assert_eq!(
+            errors.clone().with_limit(1).to_string(),
+            "a\n\nand 2 more diagnostics\n"
+        );
+        assert_eq!(
+            errors.clone().with_limit(2).to_string(),
+            "a\n\nb\n\nand 1 more diagnostic\n"
+        );
+        assert_eq!(errors.with_limit(0).to_string(), "and 3 more diagnostics\n");
+    }
+
+    #[test]
+    fn no_source() {
+        let errors = Errors::from(Error::new("a"));
+
+        assert!(std::error::Error::source(&errors).is_none());
+    }
+}
@@ -0,0 +1,448 @@
+//! Fuzzing entry points for lexers and grammars, enabled with the `fuzz` feature.
+//!
+//! [`lex`] and [`parse`] run a [`Token`] or [`Parse`] type over arbitrary bytes and panic
+//! when it misbehaves: a lexer that returns a token without consuming input or before the
+//! end of the previous token, a parser that stops making progress, or a span that points
+//! outside of the source. Panics in user code,
+//! like joining unrelated spans, surface as is. Both are meant to be called from a
+//! `cargo fuzz` target:
+//!
+//! ```ignore
+//! fuzz_target!(|data: &[u8]| lasagna::fuzz::parse::<Program>(data));
+//! ```
+//!
+//! [`Corpus`] runs the same checks deterministically over a directory of inputs and seeded
+//! mutations of them, so a regular test catches regressions without a fuzzer.
+
+use std::{
+    fmt, fs, io,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    parser::expected_eof, AstDump, Error, Errors, Generator, Lexer, Parse, Parser, SharedSource,
+    SkipWhitespace, SourcePath, Span, SpannedToken, Token, TokenKind, TokenSet,
+};
+
+/// Path given to fuzzed sources.
+const PATH: SourcePath = SourcePath::Other("fuzz");
+
+/// Number of parser steps allowed without consuming a new token.
+pub const STEP_LIMIT: usize = 100_000;
+
+/// Lexes `data` as a sequence of `T`, skipping whitespace and recovering from errors by
+/// skipping a character.
+///
+/// Panics if a token is returned without consuming input, if a token or error points outside
+/// of the source, or if a token starts before the end of the previous one.
+pub fn lex<T: Token<char>>(data: &[u8]) {
+    let source = SharedSource::new(String::from_utf8_lossy(data));
+    let length = source.text().chars().count();
+    let mut lexer = source.lexer(PATH);
+    let mut prev_end = 0;
+
+    loop {
+        while lexer.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+            lexer.consume();
+        }
+
+        if lexer.is_empty() {
+            break;
+        }
+
+        let start = lexer.span(0);
+
+        match lexer.attempt(|lexer| T::lex(lexer)) {
+            Ok(_) => {
+                let end = lexer.span(0);
+
+                if end.offset <= start.offset {
+                    panic!(
+                        "{}::lex returned a token without consuming input at {:?}",
+                        std::any::type_name::<T>(),
+                        start
+                    );
+                }
+
+                if start.offset < prev_end {
+                    panic!(
+                        "{}::lex returned a token at {:?} before the end of the previous token at \
+                         offset {}",
+                        std::any::type_name::<T>(),
+                        start,
+                        prev_end
+                    );
+                }
+
+                check_span(start | end, length);
+                prev_end = end.offset;
+            }
+            Err(err) => {
+                check_error(&err, length);
+                lexer.consume();
+            }
+        }
+    }
+}
+
+/// Parses `data` as `P`, followed by the end of input.
+///
+/// Panics if the parser stops making progress, or if a span of the parsed node or of the
+/// error points outside of the source.
+pub fn parse<P>(data: &[u8])
+where
+    P: Parse,
+    P::Token: Token<char>,
+{
+    let source = SharedSource::new(String::from_utf8_lossy(data));
+    let length = source.text().chars().count();
+    let lexer = source.lexer(PATH);
+    let mut parser = Watchdog::new(SkipWhitespace::new(lexer).with_no_progress_panics(false));
+
+    let result = parser.parse::<P>().and_then(|node| match parser.peek()? {
+        Some(token) => {
+            let (span, found) = (token.span, token.kind().label());
+
+            Err(expected_eof(span, parser.expected_at(span), found))
+        }
+        None => Ok(node),
+    });
+
+    match result {
+        Ok(node) => {
+            let mut dump = AstDump::new();
+            node.dump(&mut dump);
+
+            for span in dump.spans() {
+                check_span(span, length);
+            }
+        }
+        Err(err) => check_error(&err, length),
+    }
+}
+
+fn check_error(err: &Error, length: usize) {
+    let spans = err.hints().iter().flat_map(|hint| hint.spans());
+
+    for &span in err.span().iter().chain(spans) {
+        check_span(span, length);
+    }
+}
+
+/// Panics if `span` is not within a fuzzed source of `length` characters.
+fn check_span(span: Span, length: usize) {
+    let span = span.origin();
+
+    if span.path != PATH {
+        return;
+    }
+
+    if span.offset + span.length > length {
+        panic!(
+            "span {:?} ({}..{}) is outside of the source of length {}",
+            span,
+            span.offset,
+            span.offset + span.length,
+            length
+        );
+    }
+}
+
+/// [`Parser`] that panics when the wrapped parser stops consuming tokens.
+struct Watchdog<P> {
+    parser: P,
+    furthest: usize,
+    steps: usize,
+}
+
+impl<P> Watchdog<P> {
+    #[inline]
+    fn new(parser: P) -> Self {
+        Self {
+            parser,
+            furthest: 0,
+            steps: 0,
+        }
+    }
+
+    #[inline]
+    fn step(&mut self) {
+        self.steps += 1;
+
+        if self.steps > STEP_LIMIT {
+            panic!(
+                "parser made no progress in {} steps after offset {}",
+                STEP_LIMIT, self.furthest
+            );
+        }
+    }
+}
+
+impl<T, P: Parser<T>> Parser<T> for Watchdog<P> {
+    type Checkpoint = P::Checkpoint;
+
+    #[inline]
+    fn span(&mut self, length: usize) -> Span {
+        self.parser.span(length)
+    }
+
+    #[inline]
+    fn prev_span(&self) -> Span {
+        self.parser.prev_span()
+    }
+
+    fn next(&mut self) -> Result<SpannedToken<T>, Error> {
+        self.step();
+
+        let token = self.parser.next()?;
+        let end = token.span.offset + token.span.length;
+
+        if end > self.furthest {
+            self.furthest = end;
+            self.steps = 0;
+        }
+
+        Ok(token)
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Result<Option<&SpannedToken<T>>, Error> {
+        self.step();
+        self.parser.peek()
+    }
+
+    #[inline]
+    fn is_empty(&mut self) -> bool {
+        self.step();
+        self.parser.is_empty()
+    }
+
+    #[inline]
+    fn enter(&mut self) -> Result<(), Error> {
+        self.parser.enter()
+    }
+
+    #[inline]
+    fn exit(&mut self) {
+        self.parser.exit();
+    }
+
+    /// Whether a lexer makes progress depends on the fuzzed input, so no progress is returned
+    /// as an error like any other instead of crashing the fuzzer.
+    #[inline]
+    fn panics_on_no_progress(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn grows_stack(&self) -> bool {
+        self.parser.grows_stack()
+    }
+
+    #[inline]
+    fn add_expected(&mut self, span: Span, expected: TokenSet<T::Kind>)
+    where
+        T: Token,
+    {
+        self.parser.add_expected(span, expected);
+    }
+
+    #[inline]
+    fn expected_at(&self, span: Span) -> TokenSet<T::Kind>
+    where
+        T: Token,
+    {
+        self.parser.expected_at(span)
+    }
+
+    #[inline]
+    fn diagnostic(&mut self, diagnostic: Error) {
+        self.parser.diagnostic(diagnostic);
+    }
+
+    #[inline]
+    fn take_diagnostics(&mut self) -> Errors {
+        self.parser.take_diagnostics()
+    }
+
+    #[inline]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.parser.checkpoint()
+    }
+
+    #[inline]
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        self.parser.rewind(checkpoint);
+    }
+}
+
+/// Deterministic fuzzing over a directory of inputs.
+///
+/// Every file in the directory is run as is, followed by a number of random edits of it
+/// derived from a fixed seed. Failures report the input that caused them.
+#[derive(Clone, Debug)]
+pub struct Corpus {
+    dir: PathBuf,
+    mutations: usize,
+    seed: u64,
+}
+
+impl Corpus {
+    #[inline]
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            mutations: 64,
+            seed: 0,
+        }
+    }
+
+    /// Sets the number of mutations run per input.
+    #[inline]
+    pub fn mutations(mut self, mutations: usize) -> Self {
+        self.mutations = mutations;
+        self
+    }
+
+    #[inline]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Runs `f` over every input and its mutations, panicking with a report of the first
+    /// failure.
+    #[track_caller]
+    pub fn run(&self, f: impl FnMut(&[u8])) {
+        if let Err(failure) = self.check(f) {
+            panic!("{}", failure);
+        }
+    }
+
+    /// Runs `f` over every input and its mutations, stopping at the first input it panics on.
+    pub fn check(&self, mut f: impl FnMut(&[u8])) -> Result<(), CorpusFailure> {
+        let inputs = self.inputs().map_err(|err| CorpusFailure {
+            path: self.dir.clone(),
+            input: None,
+            message: format!("failed to read corpus: {}", err),
+        })?;
+
+        for (path, data) in inputs {
+            let mut generator = Generator::new(self.seed);
+
+            Self::case(&path, &data, &mut f)?;
+
+            for _ in 0..self.mutations {
+                let mut mutated = mutate(&data, &mut generator);
+
+                for _ in 0..generator.choose(4) {
+                    mutated = mutate(&mutated, &mut generator);
+                }
+
+                Self::case(&path, &mutated, &mut f)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn case(path: &Path, data: &[u8], f: &mut impl FnMut(&[u8])) -> Result<(), CorpusFailure> {
+        panic::catch_unwind(AssertUnwindSafe(|| f(data))).map_err(|payload| {
+            let message = match payload.downcast::<String>() {
+                Ok(message) => *message,
+                Err(payload) => match payload.downcast::<&str>() {
+                    Ok(message) => message.to_string(),
+                    Err(_) => String::from("panicked"),
+                },
+            };
+
+            CorpusFailure {
+                path: path.to_path_buf(),
+                input: Some(data.to_vec()),
+                message,
+            }
+        })
+    }
+
+    /// Returns the files in the directory, sorted by path.
+    fn inputs(&self) -> io::Result<Vec<(PathBuf, Vec<u8>)>> {
+        let mut inputs = Vec::new();
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+
+            if path.is_file() {
+                let data = fs::read(&path)?;
+                inputs.push((path, data));
+            }
+        }
+
+        inputs.sort();
+
+        Ok(inputs)
+    }
+}
+
+/// A corpus input that failed, see [`Corpus::check`].
+#[derive(Clone, Debug)]
+pub struct CorpusFailure {
+    /// Corpus file the input was derived from.
+    pub path: PathBuf,
+    /// The failing input, `None` if the corpus couldn't be read.
+    pub input: Option<Vec<u8>>,
+    /// Panic message of the failure.
+    pub message: String,
+}
+
+impl fmt::Display for CorpusFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.input {
+            Some(ref input) => write!(
+                f,
+                "fuzz case derived from '{}' panicked on input {:?}: {}",
+                self.path.display(),
+                String::from_utf8_lossy(input),
+                self.message
+            ),
+            None => write!(f, "'{}': {}", self.path.display(), self.message),
+        }
+    }
+}
+
+/// Applies a random edit to `data`: deleting, duplicating, swapping or inserting bytes.
+pub fn mutate(data: &[u8], generator: &mut Generator) -> Vec<u8> {
+    const INTERESTING: &[u8] = b"\0\n \"\\{}[]()=,;";
+
+    let mut data = data.to_vec();
+    let len = data.len();
+
+    match generator.choose(5) {
+        0 if len > 0 => {
+            let start = generator.choose(len);
+            let end = start + generator.choose(len - start) + 1;
+            data.drain(start..end);
+        }
+        1 if len > 0 => {
+            let start = generator.choose(len);
+            let end = start + generator.choose(len - start) + 1;
+            let range = data[start..end].to_vec();
+            data.splice(start..start, range);
+        }
+        2 if len > 1 => {
+            let a = generator.choose(len);
+            let b = generator.choose(len);
+            data.swap(a, b);
+        }
+        3 => {
+            let byte = (generator.next_u64() & 0xff) as u8;
+            data.insert(generator.choose(len + 1), byte);
+        }
+        _ => {
+            let byte = INTERESTING[generator.choose(INTERESTING.len())];
+            data.insert(generator.choose(len + 1), byte);
+        }
+    }
+
+    data
+}
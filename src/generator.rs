@@ -0,0 +1,151 @@
+use crate::{AstDump, Printer};
+
+/// Generator of random token sequences, see [`Parse::generate`](crate::Parse::generate).
+///
+/// Besides the source, the generator records the tree it chose in the format of
+/// [`Parse::dump`](crate::Parse::dump) without spans, so the tree a source parses to can be
+/// compared against the tree it was generated from. Implementations of `generate` should
+/// write the same nodes, fields, tokens and leaves as their `dump`.
+///
+/// Generation fails when the nesting depth exceeds the limit, or when a node can't be
+/// generated, e.g. an extern token without `#[token(sample = "...")]`.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    state: u64,
+    depth: usize,
+    max_depth: usize,
+    failure: Option<String>,
+    printer: Printer,
+    dump: AstDump,
+}
+
+/// Source produced by a [`Generator`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Generated {
+    pub source: String,
+    /// Dump of the generated tree without spans, see [`AstDump::without_spans`].
+    pub structure: String,
+}
+
+impl Generator {
+    pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+    #[inline]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            depth: 0,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            failure: None,
+            printer: Printer::new(),
+            dump: AstDump::without_spans(),
+        }
+    }
+
+    #[inline]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Returns a random number, using splitmix64.
+    #[inline]
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number in `0..n`.
+    #[inline]
+    pub fn choose(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    /// Decides whether to generate an optional node, always `false` at half the depth limit.
+    #[inline]
+    pub fn optional(&mut self) -> bool {
+        self.depth * 2 < self.max_depth && self.choose(2) == 0
+    }
+
+    /// Returns the number of items to generate for a repetition.
+    #[inline]
+    pub fn repeat(&mut self) -> usize {
+        if self.depth * 2 < self.max_depth {
+            self.choose(4)
+        } else {
+            0
+        }
+    }
+
+    /// Generates a nested node named `name` with `f`, failing if the depth limit is exceeded.
+    pub fn node(&mut self, name: impl Into<String>, f: impl FnOnce(&mut Self)) {
+        if self.failed() {
+            return;
+        }
+
+        if self.depth >= self.max_depth {
+            self.fail("maximum depth exceeded");
+            return;
+        }
+
+        let index = self.dump.open(name.into());
+
+        self.depth += 1;
+        f(self);
+        self.depth -= 1;
+
+        self.dump.close(index);
+    }
+
+    /// Sets the field name of the next node, see [`AstDump::field`].
+    #[inline]
+    pub fn field(&mut self, label: &'static str) {
+        self.dump.field(label);
+    }
+
+    /// Writes a token of kind `name` with `text`.
+    #[inline]
+    pub fn token(&mut self, name: impl Into<String>, text: &str) {
+        if !self.failed() {
+            self.printer.token(text);
+            self.dump.token_text(name, text);
+        }
+    }
+
+    /// Writes a node that has no tokens, like a missing optional node.
+    #[inline]
+    pub fn leaf(&mut self, name: impl Into<String>) {
+        if !self.failed() {
+            self.dump.leaf(name);
+        }
+    }
+
+    /// Marks the generation as failed.
+    #[inline]
+    pub fn fail(&mut self, reason: impl Into<String>) {
+        if self.failure.is_none() {
+            self.failure = Some(reason.into());
+        }
+    }
+
+    #[inline]
+    pub fn failed(&self) -> bool {
+        self.failure.is_some()
+    }
+
+    /// Returns the generated source and tree, or the reason generation failed.
+    #[inline]
+    pub fn finish(self) -> Result<Generated, String> {
+        match self.failure {
+            Some(failure) => Err(failure),
+            None => Ok(Generated {
+                source: self.printer.finish(),
+                structure: self.dump.finish(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,628 @@
+use std::fmt::{self, Write};
+
+use crate::Parse;
+
+/// Expression in a [`Grammar`], see [`Parse::grammar`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GrammarExpr {
+    /// Matches nothing.
+    Empty,
+    /// A literal token, like `"{"`.
+    Terminal(String),
+    /// A token matched by a lexer, like a string literal.
+    Token(String),
+    /// A reference to a rule of the grammar.
+    NonTerminal(String),
+    Sequence(Vec<GrammarExpr>),
+    Choice(Vec<GrammarExpr>),
+    Optional(Box<GrammarExpr>),
+    /// Zero or more repetitions.
+    Repeat(Box<GrammarExpr>),
+}
+
+impl GrammarExpr {
+    #[inline]
+    pub fn optional(expr: Self) -> Self {
+        Self::Optional(Box::new(expr))
+    }
+
+    #[inline]
+    pub fn repeat(expr: Self) -> Self {
+        Self::Repeat(Box::new(expr))
+    }
+
+    fn write_ebnf(&self, out: &mut String, nested: bool) {
+        match self {
+            Self::Empty => out.push_str("\"\""),
+            Self::Terminal(text) => out.push_str(&quote(text)),
+            Self::Token(name) | Self::NonTerminal(name) => write_identifier(name, out),
+            Self::Sequence(exprs) => {
+                if nested && exprs.len() > 1 {
+                    out.push_str("( ");
+                }
+
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(" , ");
+                    }
+
+                    expr.write_ebnf(out, true);
+                }
+
+                if nested && exprs.len() > 1 {
+                    out.push_str(" )");
+                }
+            }
+            Self::Choice(exprs) => {
+                if nested && exprs.len() > 1 {
+                    out.push_str("( ");
+                }
+
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(" | ");
+                    }
+
+                    expr.write_ebnf(out, exprs.len() > 1);
+                }
+
+                if nested && exprs.len() > 1 {
+                    out.push_str(" )");
+                }
+            }
+            Self::Optional(expr) => {
+                out.push_str("[ ");
+                expr.write_ebnf(out, false);
+                out.push_str(" ]");
+            }
+            Self::Repeat(expr) => {
+                out.push_str("{ ");
+                expr.write_ebnf(out, false);
+                out.push_str(" }");
+            }
+        }
+    }
+}
+
+/// A named rule of a [`Grammar`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GrammarRule {
+    pub name: String,
+    pub expr: GrammarExpr,
+}
+
+/// Description of the grammar of a [`Parse`] type, built with [`Parse::grammar`].
+///
+/// Every derived type is a rule, rules are in the order they were first reached from the root.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Grammar {
+    rules: Vec<GrammarRule>,
+}
+
+impl Grammar {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the grammar of `P`, with `P` as the first rule.
+    pub fn of<P: Parse>() -> Self {
+        let mut grammar = Self::new();
+        let root = P::grammar(&mut grammar);
+
+        if !matches!(root, GrammarExpr::NonTerminal(_)) {
+            grammar.rules.insert(
+                0,
+                GrammarRule {
+                    name: String::from("root"),
+                    expr: root,
+                },
+            );
+        }
+
+        grammar
+    }
+
+    /// Adds the rule `name` if it doesn't exist, `f` returns its expression.
+    ///
+    /// Returns a reference to the rule, so recursive rules terminate.
+    pub fn rule(
+        &mut self,
+        name: impl Into<String>,
+        f: impl FnOnce(&mut Self) -> GrammarExpr,
+    ) -> GrammarExpr {
+        let name = name.into();
+
+        if self.get(&name).is_none() {
+            let index = self.rules.len();
+
+            self.rules.push(GrammarRule {
+                name: name.clone(),
+                expr: GrammarExpr::Empty,
+            });
+
+            self.rules[index].expr = f(self);
+        }
+
+        GrammarExpr::NonTerminal(name)
+    }
+
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&GrammarRule> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+
+    #[inline]
+    pub fn rules(&self) -> &[GrammarRule] {
+        &self.rules
+    }
+
+    /// Writes the grammar as ISO EBNF, one rule per line.
+    pub fn to_ebnf(&self) -> String {
+        let mut out = String::new();
+
+        for rule in &self.rules {
+            write_identifier(&rule.name, &mut out);
+            out.push_str(" = ");
+            rule.expr.write_ebnf(&mut out, false);
+            out.push_str(" ;\n");
+        }
+
+        out
+    }
+
+    /// Renders the grammar as railroad diagrams in a single SVG document.
+    pub fn to_svg(&self) -> String {
+        let mut body = String::new();
+        let mut width = 0;
+        let mut y = MARGIN;
+
+        for rule in &self.rules {
+            let size = Size::of(&rule.expr);
+
+            let _ = writeln!(
+                body,
+                r#"<text class="rule" x="{}" y="{}">{}</text>"#,
+                MARGIN,
+                y + 14,
+                Escape(&rule.name)
+            );
+            y += TITLE_HEIGHT;
+
+            let baseline = y + size.up.max(BOX_HALF_HEIGHT);
+            let start = MARGIN + END_WIDTH;
+
+            let _ = writeln!(
+                body,
+                r#"<path d="M{} {} v20 m10 -20 v20 m-10 -10 h{}"/>"#,
+                MARGIN,
+                baseline - 10,
+                END_WIDTH
+            );
+
+            render(&rule.expr, start, baseline, &mut body);
+
+            let end = start + size.width;
+
+            let _ = writeln!(
+                body,
+                r#"<path d="M{} {} h{} m0 -10 v20 m10 -20 v20"/>"#,
+                end,
+                baseline,
+                END_WIDTH - 10
+            );
+
+            width = width.max(end + END_WIDTH + MARGIN);
+            y = baseline + size.down + MARGIN;
+        }
+
+        format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+                "\n<style>{style}</style>\n{body}</svg>\n"
+            ),
+            w = width,
+            h = y,
+            style = STYLE,
+            body = body,
+        )
+    }
+}
+
+impl fmt::Display for Grammar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_ebnf())
+    }
+}
+
+const STYLE: &str = "path, rect { fill: none; stroke: black; stroke-width: 2; } \
+text { font: 14px monospace; text-anchor: middle; dominant-baseline: central; } \
+text.rule { font-weight: bold; text-anchor: start; dominant-baseline: auto; }";
+
+const MARGIN: i64 = 10;
+const TITLE_HEIGHT: i64 = 24;
+const END_WIDTH: i64 = 20;
+const RADIUS: i64 = 10;
+const GAP: i64 = 10;
+const CHAR_WIDTH: i64 = 9;
+const BOX_PADDING: i64 = 10;
+const BOX_HALF_HEIGHT: i64 = 12;
+
+/// Extent of a diagram around its baseline.
+#[derive(Clone, Copy)]
+struct Size {
+    width: i64,
+    up: i64,
+    down: i64,
+}
+
+impl Size {
+    const EMPTY: Self = Self {
+        width: 0,
+        up: 0,
+        down: 0,
+    };
+
+    fn of(expr: &GrammarExpr) -> Self {
+        match expr {
+            GrammarExpr::Empty => Self::EMPTY,
+            GrammarExpr::Terminal(text) => Self::text(&label(text)),
+            GrammarExpr::Token(text) | GrammarExpr::NonTerminal(text) => Self::text(text),
+            GrammarExpr::Sequence(exprs) => {
+                exprs
+                    .iter()
+                    .map(Self::of)
+                    .fold(None, |acc, size| match acc {
+                        None => Some(size),
+                        Some(acc) => Some(Self {
+                            width: acc.width + GAP + size.width,
+                            up: acc.up.max(size.up),
+                            down: acc.down.max(size.down),
+                        }),
+                    })
+            }
+            .unwrap_or(Self::EMPTY),
+            GrammarExpr::Choice(exprs) => Self::choice(exprs.iter().map(Self::of)),
+            GrammarExpr::Optional(expr) => Self::choice([Self::EMPTY, Self::of(expr)]),
+            GrammarExpr::Repeat(expr) => Self::choice([Self::EMPTY, Self::one_or_more(expr)]),
+        }
+    }
+
+    fn text(text: &str) -> Self {
+        Self {
+            width: text.chars().count() as i64 * CHAR_WIDTH + 2 * BOX_PADDING,
+            up: BOX_HALF_HEIGHT,
+            down: BOX_HALF_HEIGHT,
+        }
+    }
+
+    fn choice(sizes: impl IntoIterator<Item = Self>) -> Self {
+        let mut sizes = sizes.into_iter();
+        let mut size = sizes.next().unwrap_or(Self::EMPTY);
+
+        for alt in sizes {
+            size.width = size.width.max(alt.width);
+            size.down += GAP + alt.up + alt.down;
+        }
+
+        size.width += 4 * RADIUS;
+        size
+    }
+
+    fn one_or_more(expr: &GrammarExpr) -> Self {
+        let size = Self::of(expr);
+
+        Self {
+            width: size.width + 2 * RADIUS,
+            up: size.up,
+            down: size.down.max(RADIUS) + RADIUS,
+        }
+    }
+}
+
+/// Renders `expr` with its baseline starting at `x`, `y`.
+fn render(expr: &GrammarExpr, x: i64, y: i64, out: &mut String) {
+    match expr {
+        GrammarExpr::Empty => {}
+        GrammarExpr::Terminal(text) => render_box(&label(text), true, x, y, out),
+        GrammarExpr::Token(text) => render_box(text, true, x, y, out),
+        GrammarExpr::NonTerminal(text) => render_box(text, false, x, y, out),
+        GrammarExpr::Sequence(exprs) => {
+            let mut x = x;
+
+            for (i, expr) in exprs.iter().enumerate() {
+                if i > 0 {
+                    line(x, y, GAP, out);
+                    x += GAP;
+                }
+
+                render(expr, x, y, out);
+                x += Size::of(expr).width;
+            }
+        }
+        GrammarExpr::Choice(exprs) => {
+            let alts: Vec<_> = exprs
+                .iter()
+                .map(|expr| (Size::of(expr), render_fn(expr)))
+                .collect();
+
+            render_choice(&alts, x, y, out);
+        }
+        GrammarExpr::Optional(expr) => {
+            let alts = [
+                (Size::EMPTY, render_fn(&GrammarExpr::Empty)),
+                (Size::of(expr), render_fn(expr)),
+            ];
+
+            render_choice(&alts, x, y, out);
+        }
+        GrammarExpr::Repeat(expr) => {
+            let alts = [
+                (Size::EMPTY, render_fn(&GrammarExpr::Empty)),
+                (
+                    Size::one_or_more(expr),
+                    Box::new(|x, y, out: &mut String| render_one_or_more(expr, x, y, out))
+                        as RenderFn<'_>,
+                ),
+            ];
+
+            render_choice(&alts, x, y, out);
+        }
+    }
+}
+
+type RenderFn<'a> = Box<dyn Fn(i64, i64, &mut String) + 'a>;
+
+fn render_fn(expr: &GrammarExpr) -> RenderFn<'_> {
+    Box::new(move |x, y, out: &mut String| render(expr, x, y, out))
+}
+
+fn render_box(text: &str, terminal: bool, x: i64, y: i64, out: &mut String) {
+    let size = Size::text(text);
+    let radius = if terminal { BOX_HALF_HEIGHT } else { 0 };
+
+    let _ = writeln!(
+        out,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}"/>"#,
+        x,
+        y - size.up,
+        size.width,
+        size.up + size.down,
+        radius
+    );
+    let _ = writeln!(
+        out,
+        r#"<text x="{}" y="{}">{}</text>"#,
+        x + size.width / 2,
+        y,
+        Escape(text)
+    );
+}
+
+fn render_choice(alts: &[(Size, RenderFn<'_>)], x: i64, y: i64, out: &mut String) {
+    let inner = alts.iter().map(|(size, _)| size.width).max().unwrap_or(0);
+    let right = x + inner + 2 * RADIUS;
+    let mut bottom = y;
+
+    for (i, (size, render)) in alts.iter().enumerate() {
+        let alt_y = if i == 0 { y } else { bottom + GAP + size.up };
+
+        if i == 0 {
+            line(x, y, 2 * RADIUS, out);
+        } else {
+            let _ = writeln!(
+                out,
+                r#"<path d="M{x} {y} a{r} {r} 0 0 1 {r} {r} V{v} a{r} {r} 0 0 0 {r} {r}"/>"#,
+                x = x,
+                y = y,
+                r = RADIUS,
+                v = alt_y - RADIUS,
+            );
+        }
+
+        render(x + 2 * RADIUS, alt_y, out);
+        line(x + 2 * RADIUS + size.width, alt_y, inner - size.width, out);
+
+        if i == 0 {
+            line(right, y, 2 * RADIUS, out);
+        } else {
+            let _ = writeln!(
+                out,
+                r#"<path d="M{x} {y} a{r} {r} 0 0 0 {r} -{r} V{v} a{r} {r} 0 0 1 {r} -{r}"/>"#,
+                x = right,
+                y = alt_y,
+                r = RADIUS,
+                v = y + RADIUS,
+            );
+        }
+
+        bottom = alt_y + size.down;
+    }
+}
+
+fn render_one_or_more(expr: &GrammarExpr, x: i64, y: i64, out: &mut String) {
+    let size = Size::of(expr);
+    let end = x + RADIUS + size.width;
+    let loop_y = y + size.down.max(RADIUS) + RADIUS;
+
+    line(x, y, RADIUS, out);
+    render(expr, x + RADIUS, y, out);
+    line(end, y, RADIUS, out);
+
+    let _ = writeln!(
+        out,
+        concat!(
+            r#"<path d="M{end} {y} a{r} {r} 0 0 1 {r} {r} V{v} a{r} {r} 0 0 1 -{r} {r} "#,
+            r#"H{start} a{r} {r} 0 0 1 -{r} -{r} V{top} a{r} {r} 0 0 1 {r} -{r}"/>"#
+        ),
+        end = end,
+        y = y,
+        r = RADIUS,
+        v = loop_y - RADIUS,
+        start = x + RADIUS,
+        top = y + RADIUS,
+    );
+}
+
+fn line(x: i64, y: i64, width: i64, out: &mut String) {
+    if width > 0 {
+        let _ = writeln!(out, r#"<path d="M{} {} h{}"/>"#, x, y, width);
+    }
+}
+
+/// Quotes `text` as an EBNF terminal.
+///
+/// EBNF has no escapes, so text containing both kinds of quotes is written as a sequence of
+/// terminals, each quoted with the kind it doesn't contain.
+fn quote(text: &str) -> String {
+    let mut out = String::new();
+    let mut segment = String::new();
+    let mut delimiter = None;
+
+    for c in text.chars() {
+        let needed = match c {
+            '"' => Some('\''),
+            '\'' => Some('"'),
+            _ => None,
+        };
+
+        if let (Some(needed), Some(current)) = (needed, delimiter) {
+            if needed != current {
+                push_terminal(&mut out, &segment, current);
+                segment.clear();
+            }
+        }
+
+        delimiter = needed.or(delimiter);
+        segment.push(c);
+    }
+
+    push_terminal(&mut out, &segment, delimiter.unwrap_or('"'));
+
+    out
+}
+
+fn push_terminal(out: &mut String, text: &str, delimiter: char) {
+    if !out.is_empty() {
+        out.push_str(" , ");
+    }
+
+    out.push(delimiter);
+    out.push_str(text);
+    out.push(delimiter);
+}
+
+/// Quotes `text` for display in a diagram, where it doesn't have to be valid EBNF.
+fn label(text: &str) -> String {
+    if text.contains('"') && !text.contains('\'') {
+        format!("'{}'", text)
+    } else {
+        format!("\"{}\"", text)
+    }
+}
+
+/// Writes `name` as an EBNF meta identifier, replacing characters EBNF doesn't allow in
+/// identifiers, like the brackets of generic arguments, with `_`.
+fn write_identifier(name: &str, out: &mut String) {
+    let mut pending = false;
+
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            if pending {
+                out.push('_');
+                pending = false;
+            }
+
+            out.push(c);
+        } else if c == ' ' {
+            if pending {
+                continue;
+            }
+
+            out.push(c);
+        } else {
+            pending = true;
+        }
+    }
+}
+
+/// Returns the name of `T` without module paths, like `Vec<Item>` for
+/// `alloc::vec::Vec<my_crate::Item>`.
+///
+/// Derived impls name generic arguments with this, it's also the name of types that don't
+/// override [`Parse::grammar`] or [`Parse::dump`].
+pub fn short_type_name<T: ?Sized>() -> String {
+    let name = std::any::type_name::<T>();
+    let mut out = String::with_capacity(name.len());
+    let mut start = 0;
+
+    for (i, c) in name.char_indices() {
+        if matches!(
+            c,
+            '<' | '>' | ',' | '(' | ')' | '[' | ']' | '&' | ';' | '*' | ' '
+        ) {
+            out.push_str(last_segment(&name[start..i]));
+            out.push(c);
+            start = i + c.len_utf8();
+        }
+    }
+
+    out.push_str(last_segment(&name[start..]));
+
+    out
+}
+
+#[inline]
+fn last_segment(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// Escapes text for XML.
+struct Escape<'a>(&'a str);
+
+impl fmt::Display for Escape<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '&' => f.write_str("&amp;")?,
+                '"' => f.write_str("&quot;")?,
+                c => f.write_char(c)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_picks_a_delimiter_the_text_doesnt_contain() {
+        assert_eq!(quote("{"), r#""{""#);
+        assert_eq!(quote(r#"a"b"#), r#"'a"b'"#);
+        assert_eq!(quote("a'b"), r#""a'b""#);
+        assert_eq!(quote(r#"a'b"c"#), r#""a'b" , '"c'"#);
+        assert_eq!(quote(r#""'"'"#), r#"'"' , "'" , '"' , "'""#);
+    }
+
+    #[test]
+    fn identifiers_are_valid_ebnf() {
+        let mut out = String::new();
+        write_identifier("Map<Key, Vec<Value>>", &mut out);
+
+        assert_eq!(out, "Map_Key_Vec_Value");
+    }
+
+    #[test]
+    fn short_type_names_have_no_paths() {
+        assert_eq!(short_type_name::<Vec<String>>(), "Vec<String>");
+        assert_eq!(
+            short_type_name::<(Option<&str>, [u8; 2])>(),
+            "(Option<&str>, [u8; 2])"
+        );
+    }
+}
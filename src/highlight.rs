@@ -0,0 +1,352 @@
+use std::fmt::Write;
+
+use crate::{Lexer, SourceRef, SourceText, Span, Token, TokenKind};
+
+/// Class given to characters that could not be lexed.
+pub const ERROR_CLASS: &str = "error";
+
+/// A run of source classified by [`highlight`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Highlight {
+    pub span: Span,
+    pub class: &'static str,
+}
+
+/// Lexes the rest of `lexer` as `T` and returns the classified runs in source order.
+///
+/// Whitespace and tokens without a class are skipped. Characters that fail to lex are
+/// classified as [`ERROR_CLASS`] and lexing resumes at the next character.
+pub fn highlight<T: Token>(lexer: &mut impl Lexer<Output = char>) -> Vec<Highlight> {
+    let mut highlights = Vec::new();
+
+    loop {
+        while lexer.peek().map(|c| c.is_whitespace()).unwrap_or(false) {
+            lexer.consume();
+        }
+
+        if lexer.is_empty() {
+            break;
+        }
+
+        let start = lexer.span(0);
+        let token = lexer.attempt(|lexer| T::lex(lexer));
+        let span = start | lexer.span(0);
+
+        match token {
+            Ok(token) if span.length > 0 => {
+                if let Some(class) = token.kind().class() {
+                    highlights.push(Highlight { span, class });
+                }
+            }
+            _ => {
+                let span = lexer.span(1);
+                lexer.consume();
+
+                push_error(&mut highlights, span);
+            }
+        }
+    }
+
+    highlights
+}
+
+/// Pushes an error run, merging it with a directly preceding error run.
+fn push_error(highlights: &mut Vec<Highlight>, span: Span) {
+    if let Some(last) = highlights.last_mut() {
+        if last.class == ERROR_CLASS && last.span.offset + last.span.length == span.offset {
+            last.span |= span;
+            return;
+        }
+    }
+
+    highlights.push(Highlight {
+        span,
+        class: ERROR_CLASS,
+    });
+}
+
+/// Splits `source` into pieces of text and the class covering them, if any.
+fn segments<'a>(source: &'a str, highlights: &[Highlight]) -> Vec<(&'a str, Option<&'static str>)> {
+    let mut segments = Vec::new();
+    let mut position = 0;
+
+    for highlight in highlights {
+        let range = highlight.span.byte_range();
+
+        if range.start < position || range.end > source.len() {
+            continue;
+        }
+
+        if range.start > position {
+            segments.push((&source[position..range.start], None));
+        }
+
+        segments.push((&source[range.clone()], Some(highlight.class)));
+        position = range.end;
+    }
+
+    if position < source.len() {
+        segments.push((&source[position..], None));
+    }
+
+    segments
+}
+
+/// Returns the ANSI escape code used by [`render_ansi`] for common classes.
+pub fn ansi_style(class: &str) -> Option<&'static str> {
+    match class {
+        "keyword" => Some("\x1b[1;35m"),
+        "string" => Some("\x1b[32m"),
+        "number" => Some("\x1b[36m"),
+        "comment" => Some("\x1b[90m"),
+        "type" => Some("\x1b[33m"),
+        "function" => Some("\x1b[34m"),
+        "operator" | "punctuation" => Some("\x1b[37m"),
+        ERROR_CLASS => Some("\x1b[4;31m"),
+        _ => None,
+    }
+}
+
+/// Renders `source` with ANSI escape codes using [`ansi_style`].
+pub fn render_ansi(source: &str, highlights: &[Highlight]) -> String {
+    render_ansi_with(source, highlights, ansi_style)
+}
+
+/// Renders `source` with ANSI escape codes chosen by `style`.
+pub fn render_ansi_with(
+    source: &str,
+    highlights: &[Highlight],
+    style: impl Fn(&str) -> Option<&'static str>,
+) -> String {
+    let mut out = String::with_capacity(source.len());
+
+    for (text, class) in segments(source, highlights) {
+        match class.and_then(&style) {
+            Some(code) => {
+                let _ = write!(out, "{}{}\x1b[0m", code, text);
+            }
+            None => out.push_str(text),
+        }
+    }
+
+    out
+}
+
+/// Renders `source` as HTML, wrapping classified runs in `<span class="tok-{class}">`.
+pub fn render_html(source: &str, highlights: &[Highlight]) -> String {
+    let mut out = String::with_capacity(source.len());
+
+    for (text, class) in segments(source, highlights) {
+        match class {
+            Some(class) => {
+                let _ = write!(out, "<span class=\"tok-{}\">", class);
+                escape_html(&mut out, text);
+                out.push_str("</span>");
+            }
+            None => escape_html(&mut out, text),
+        }
+    }
+
+    out
+}
+
+fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Highlights encoded as LSP semantic tokens.
+///
+/// Positions are in UTF-16 code units and runs spanning several lines are split per line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SemanticTokens {
+    /// Relative encoded tokens, five integers per token.
+    pub data: Vec<u32>,
+}
+
+impl SemanticTokens {
+    /// Encodes `highlights` of one source, token types are indices into `legend`.
+    ///
+    /// The runs are encoded in source order whatever their order in `highlights`, since the
+    /// encoding is relative to the previous run. Runs whose class isn't in `legend` and runs
+    /// overlapping an earlier run are skipped.
+    pub fn encode(highlights: &[Highlight], legend: &[&str]) -> Self {
+        let mut tokens = Self::default();
+        let mut prev = (0, 0);
+        let mut end = 0;
+        let mut cursor = ColumnCursor::default();
+
+        let mut sorted = highlights.iter().collect::<Vec<_>>();
+        sorted.sort_by_key(|highlight| highlight.span.offset);
+
+        for highlight in sorted {
+            if highlight.span.offset < end {
+                continue;
+            }
+
+            let token_type = match legend.iter().position(|class| *class == highlight.class) {
+                Some(index) => index as u32,
+                None => continue,
+            };
+
+            let span = highlight.span;
+            let range = span.byte_range();
+            end = span.offset + span.length;
+            let mut column = cursor.column(span.source, range.start);
+            let lines = cursor.text()[range].split('\n');
+
+            for (line, text) in (span.line as u32..).zip(lines) {
+                let length = utf16_len(text.strip_suffix('\r').unwrap_or(text));
+
+                if length > 0 {
+                    let delta_line = line - prev.0;
+                    let delta_start = if delta_line == 0 {
+                        column - prev.1
+                    } else {
+                        column
+                    };
+
+                    tokens.data.extend_from_slice(&[
+                        delta_line,
+                        delta_start,
+                        length,
+                        token_type,
+                        0,
+                    ]);
+                    prev = (line, column);
+                }
+
+                column = 0;
+            }
+        }
+
+        tokens
+    }
+}
+
+/// Tracks the UTF-16 column of a byte position, so that encoding highlights in source order
+/// walks the source once.
+struct ColumnCursor {
+    source: Option<SourceText>,
+    text: SourceRef,
+    byte: usize,
+    column: u32,
+}
+
+impl Default for ColumnCursor {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            source: None,
+            text: SourceRef::Static(""),
+            byte: 0,
+            column: 0,
+        }
+    }
+}
+
+impl ColumnCursor {
+    fn column(&mut self, source: SourceText, byte: usize) -> u32 {
+        if self.source != Some(source) {
+            self.source = Some(source);
+            self.text = source.get();
+            self.byte = usize::MAX;
+        }
+
+        if byte < self.byte {
+            self.byte = self.text[..byte].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            self.column = 0;
+        }
+
+        for c in self.text[self.byte..byte].chars() {
+            if c == '\n' {
+                self.column = 0;
+            } else {
+                self.column += c.len_utf16() as u32;
+            }
+        }
+
+        self.byte = byte;
+        self.column
+    }
+
+    /// Returns the text of the source last passed to [`ColumnCursor::column`].
+    #[inline]
+    fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharsLexer, SourcePath};
+
+    fn spans(source: &'static str) -> Vec<Span> {
+        let mut lexer = CharsLexer::new_static(source, SourcePath::Generated);
+        let mut spans = Vec::new();
+
+        while !lexer.is_empty() {
+            spans.push(lexer.span(1));
+            lexer.consume();
+        }
+
+        spans
+    }
+
+    #[test]
+    fn encode_counts_utf16_columns() {
+        let spans = spans("é a\n𝔸b");
+        let highlights = [spans[2], spans[5]]
+            .iter()
+            .map(|&span| Highlight {
+                span,
+                class: "keyword",
+            })
+            .collect::<Vec<_>>();
+
+        let tokens = SemanticTokens::encode(&highlights, &["keyword"]);
+
+        assert_eq!(tokens.data, [0, 2, 1, 0, 0, 1, 2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn encode_sorts_and_skips_overlaps() {
+        let spans = spans("ab\ncd");
+        let highlight = |span| Highlight {
+            span,
+            class: "keyword",
+        };
+        let highlights = [
+            highlight(spans[4]),
+            highlight(spans[1]),
+            highlight(spans[0] | spans[1]),
+            highlight(spans[0]),
+        ];
+
+        let tokens = SemanticTokens::encode(&highlights, &["keyword"]);
+
+        assert_eq!(tokens.data, [0, 0, 2, 0, 0, 1, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn byte_range_uses_byte_offset() {
+        let spans = spans("é a\n𝔸b");
+
+        assert_eq!(spans[2].text(), "a");
+        assert_eq!(spans[5].text(), "b");
+        assert_eq!((spans[4] | spans[5]).text(), "𝔸b");
+    }
+}
@@ -0,0 +1,130 @@
+//! Machine readable diagnostics, enabled with the `json` feature.
+//!
+//! [`diagnostic`] converts an [`Error`] to the shape rustc emits with `--error-format=json`,
+//! hints and suggestions become children, and suggestions carry a `suggested_replacement`.
+//! [`write_diagnostic`] and [`write_diagnostics`] write one diagnostic per line, so the output
+//! can be consumed the same way as rustc's. See [`lsp::diagnostic`](crate::lsp::diagnostic) for the LSP shape.
+
+use std::io::{self, Write};
+
+use serde_json::{json, Value};
+
+use crate::{Error, Errors, Span};
+
+/// Converts an [`Error`] to a rustc JSON diagnostic.
+pub fn diagnostic(error: &Error) -> Value {
+    let spans: Vec<_> = error
+        .span()
+        .into_iter()
+        .map(|span| diagnostic_span(span, true, None, None))
+        .collect();
+
+    let mut children = Vec::new();
+
+    if let Some(node) = error.node() {
+        children.push(child(format!("while parsing {}", node), "note", Vec::new()));
+    }
+
+    let mut source = std::error::Error::source(error);
+
+    while let Some(error) = source {
+        let message = match error.downcast_ref::<Error>() {
+            Some(error) => format!("caused by: {}", error.msg()),
+            None => format!("caused by: {}", error),
+        };
+
+        children.push(child(message, "note", Vec::new()));
+        source = error.source();
+    }
+
+    for hint in error.hints() {
+        let spans = hint
+            .spans()
+            .iter()
+            .map(|&span| diagnostic_span(span, false, Some(hint.msg()), None))
+            .collect();
+
+        children.push(child(hint.msg(), "note", spans));
+    }
+
+    for suggestion in error.suggestions() {
+        let span = diagnostic_span(
+            suggestion.span,
+            true,
+            None,
+            Some(suggestion.replacement.as_str()),
+        );
+
+        children.push(child(&suggestion.msg, "help", vec![span]));
+    }
+
+    // rustc always emits the field, `null` without a code
+    let code = error
+        .code()
+        .map(|code| json!({ "code": code, "explanation": null }));
+
+    json!({
+        "$message_type": "diagnostic",
+        "message": error.msg(),
+        "code": code,
+        "level": error.severity().as_str(),
+        "spans": spans,
+        "children": children,
+        "rendered": error.to_string(),
+    })
+}
+
+/// Writes `error` as a rustc JSON diagnostic on one line.
+pub fn write_diagnostic(output: &mut impl Write, error: &Error) -> io::Result<()> {
+    writeln!(output, "{}", diagnostic(error))
+}
+
+/// Writes every error in `errors` as a rustc JSON diagnostic, one per line.
+///
+/// Unlike displaying [`Errors`], this isn't capped by [`Errors::limit`].
+pub fn write_diagnostics(output: &mut impl Write, errors: &Errors) -> io::Result<()> {
+    for error in errors {
+        write_diagnostic(output, error)?;
+    }
+
+    Ok(())
+}
+
+fn child(message: impl ToString, level: &str, spans: Vec<Value>) -> Value {
+    json!({
+        "message": message.to_string(),
+        "code": null,
+        "level": level,
+        "spans": spans,
+        "children": [],
+        "rendered": null,
+    })
+}
+
+/// Converts a [`Span`] to a rustc span, lines and columns are one-based.
+fn diagnostic_span(
+    span: Span,
+    is_primary: bool,
+    label: Option<&str>,
+    replacement: Option<&str>,
+) -> Value {
+    let span = span.origin();
+    let range = span.byte_range();
+    let (line_end, column_end) = span.end();
+
+    json!({
+        "file_name": span.path.to_string(),
+        "byte_start": range.start,
+        "byte_end": range.end,
+        "line_start": span.line + 1,
+        "line_end": line_end + 1,
+        "column_start": span.column + 1,
+        "column_end": column_end + 1,
+        "is_primary": is_primary,
+        "text": [],
+        "label": label,
+        "suggested_replacement": replacement,
+        "suggestion_applicability": replacement.map(|_| "MaybeIncorrect"),
+        "expansion": null,
+    })
+}
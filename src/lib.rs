@@ -1,16 +1,66 @@
+mod boxed;
+mod delimited;
+mod dump;
 mod error;
+mod errors;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+mod generator;
+mod grammar;
+mod highlight;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+mod option;
 mod parser;
+mod printer;
+mod source;
+mod source_map;
 mod span;
 mod string_allocator;
+mod symbol;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod token;
+mod token_set;
+#[cfg(feature = "trace")]
+pub mod trace;
+mod tuple;
 mod vec;
 
+pub use delimited::*;
+pub use dump::*;
 pub use error::*;
+pub use errors::*;
+pub use generator::*;
+pub use grammar::*;
+pub use highlight::*;
 pub use parser::*;
+pub use printer::*;
+pub use source::*;
+pub use source_map::*;
 pub use span::*;
+pub use symbol::*;
 pub use token::*;
-pub use vec::*;
+pub use token_set::*;
 
 pub use lasagna_derive::*;
 
 pub use regex;
+
+#[cfg(not(feature = "trace"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace_parse {
+    ($ty:ty, $parser:ident, $body:expr) => {
+        $body
+    };
+}
+
+#[cfg(not(feature = "trace"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace_variant {
+    ($ty:ty, $variant:expr) => {};
+}
@@ -0,0 +1,428 @@
+//! Language server scaffolding, enabled with the `lsp` feature.
+//!
+//! [`Server`] speaks the language server protocol over any reader and writer, parses every
+//! open document with a root [`Parse`] type on change and publishes the resulting [`Error`]s
+//! as diagnostics. Nodes marked with `#[parse(symbol = "...")]` are reported as document
+//! symbols, and tokens classified with `#[token(class = "...")]` as semantic tokens.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    marker::PhantomData,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    highlight, parser::parse_lexer_with_diagnostics, string_allocator::static_str, Error, Parse,
+    Parsed, SemanticTokens, SharedSource, SourcePath, Span, Symbol, Token, TokenKind,
+};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Language server for documents with the root node `P`.
+pub struct Server<P> {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+    marker: PhantomData<fn() -> P>,
+}
+
+/// An open document, owned by the server so that its text is freed when it changes or closes.
+///
+/// The path is interned when the document is opened and kept across changes.
+struct Document {
+    path: SourcePath,
+    source: SharedSource,
+}
+
+impl Document {
+    #[inline]
+    fn new(uri: &str, text: &str) -> Self {
+        Self {
+            path: SourcePath::Other(static_str(uri)),
+            source: SharedSource::new(text),
+        }
+    }
+
+    #[inline]
+    fn parse<P>(&self) -> Result<Parsed<P>, crate::Errors>
+    where
+        P: Parse,
+        P::Token: Token<char>,
+    {
+        parse_lexer_with_diagnostics(self.source.lexer(self.path))
+    }
+}
+
+impl<P> Default for Server<P> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            documents: HashMap::new(),
+            shutdown: false,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<P> Server<P>
+where
+    P: Parse,
+    P::Token: Token<char>,
+{
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs the server on stdin and stdout until the client sends `exit`.
+    pub fn run_stdio(&mut self) -> io::Result<()> {
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+
+        self.run(stdin.lock(), stdout.lock())
+    }
+
+    /// Runs the server until the client sends `exit` or `input` is closed.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        while let Some(message) = read_message(&mut input)? {
+            // responses to requests sent by the server have no method and get no reply
+            let method = match message["method"].as_str() {
+                Some(method) => method,
+                None => continue,
+            };
+
+            if method == "exit" {
+                break;
+            }
+
+            let id = message.get("id").cloned();
+            let result = self.handle(method, &message["params"], &mut output)?;
+
+            if let Some(id) = id {
+                let response = match result {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": code, "message": message },
+                    }),
+                };
+
+                write_message(&mut output, &response)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the client has requested a shutdown.
+    #[inline]
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown
+    }
+
+    /// Returns the text of an open document.
+    #[inline]
+    pub fn document(&self, uri: &str) -> Option<&str> {
+        self.documents
+            .get(uri)
+            .map(|document| document.source.text())
+    }
+
+    fn handle(
+        &mut self,
+        method: &str,
+        params: &Value,
+        output: &mut impl Write,
+    ) -> io::Result<Result<Value, (i64, String)>> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "documentSymbolProvider": true,
+                    "semanticTokensProvider": {
+                        "legend": {
+                            "tokenTypes": <<P::Token as Token>::Kind as TokenKind>::CLASSES,
+                            "tokenModifiers": [],
+                        },
+                        "full": true,
+                    },
+                },
+                "serverInfo": { "name": "lasagna" },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+
+                Ok(Value::Null)
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents
+                    .insert(uri.to_string(), Document::new(uri, text));
+                self.publish_diagnostics(uri, output)?;
+
+                Ok(Value::Null)
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+
+                if let Some(text) = changes.and_then(|c| c.last()?["text"].as_str()) {
+                    match self.documents.get_mut(uri) {
+                        Some(document) => document.source = SharedSource::new(text),
+                        None => {
+                            self.documents
+                                .insert(uri.to_string(), Document::new(uri, text));
+                        }
+                    }
+                }
+
+                self.publish_diagnostics(uri, output)?;
+
+                Ok(Value::Null)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+
+                let params = json!({ "uri": uri, "diagnostics": [] });
+                write_notification(output, "textDocument/publishDiagnostics", params)?;
+
+                Ok(Value::Null)
+            }
+            "textDocument/documentSymbol" => match self.documents.get(uri) {
+                Some(document) => {
+                    let mut symbols = Vec::new();
+
+                    if let Ok(parsed) = document.parse::<P>() {
+                        parsed.node.symbols(&mut symbols);
+                    }
+
+                    Ok(Value::Array(symbols.iter().map(document_symbol).collect()))
+                }
+                None => Err((INVALID_PARAMS, format!("unknown document '{}'", uri))),
+            },
+            "textDocument/semanticTokens/full" => match self.documents.get(uri) {
+                Some(document) => {
+                    let mut lexer = document.source.lexer(document.path);
+                    let highlights = highlight::<P::Token>(&mut lexer);
+                    let classes = <<P::Token as Token>::Kind as TokenKind>::CLASSES;
+                    let tokens = SemanticTokens::encode(&highlights, classes);
+
+                    Ok(json!({ "data": tokens.data }))
+                }
+                None => Err((INVALID_PARAMS, format!("unknown document '{}'", uri))),
+            },
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+        };
+
+        Ok(result)
+    }
+
+    fn publish_diagnostics(&self, uri: &str, output: &mut impl Write) -> io::Result<()> {
+        let diagnostics = match self.documents.get(uri) {
+            Some(document) => match document.parse::<P>() {
+                Ok(parsed) => parsed
+                    .diagnostics
+                    .iter()
+                    .map(|error| diagnostic(error, uri))
+                    .collect(),
+                Err(errors) => errors.iter().map(|error| diagnostic(error, uri)).collect(),
+            },
+            None => Vec::new(),
+        };
+
+        let params = json!({ "uri": uri, "diagnostics": diagnostics });
+        write_notification(output, "textDocument/publishDiagnostics", params)
+    }
+}
+
+/// Converts a [`Span`] to an LSP range, positions are in UTF-16 code units.
+pub fn span_range(span: Span) -> Value {
+    let range = span.byte_range();
+    let source = span.source.get();
+
+    json!({
+        "start": position(&source, range.start),
+        "end": position(&source, range.end),
+    })
+}
+
+fn position(source: &str, byte: usize) -> Value {
+    let before = &source[..byte];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let character = before[line_start..].encode_utf16().count();
+
+    json!({ "line": line, "character": character })
+}
+
+/// Returns the uri of the source of `span`, or `document` for generated sources.
+fn span_uri(span: Span, document: &str) -> String {
+    match span.path {
+        SourcePath::Path(path) => format!("file://{}", path.display()),
+        SourcePath::Other(uri) => uri.to_string(),
+        SourcePath::Generated | SourcePath::Expansion(_) => document.to_string(),
+    }
+}
+
+/// Converts an [`Error`] to an LSP diagnostic, spanned hints become related information.
+pub fn diagnostic(error: &Error, uri: &str) -> Value {
+    let span = error
+        .span()
+        .or_else(|| error.hints().iter().find_map(|hint| hint.span()))
+        .map(|span| span.origin());
+
+    let range = match span {
+        Some(span) => span_range(span),
+        None => json!({
+            "start": { "line": 0, "character": 0 },
+            "end": { "line": 0, "character": 0 },
+        }),
+    };
+
+    let mut message = error.msg().to_string();
+    let mut related = Vec::new();
+
+    let mut source = std::error::Error::source(error);
+
+    while let Some(error) = source {
+        match error.downcast_ref::<Error>() {
+            Some(error) => message.push_str(&format!("\ncaused by: {}", error.msg())),
+            None => message.push_str(&format!("\ncaused by: {}", error)),
+        }
+
+        source = error.source();
+    }
+
+    for hint in error.hints() {
+        if hint.spans().is_empty() {
+            message.push('\n');
+            message.push_str(hint.msg());
+        }
+
+        for span in hint.spans().iter().map(Span::origin) {
+            related.push(json!({
+                "location": { "uri": span_uri(span, uri), "range": span_range(span) },
+                "message": hint.msg(),
+            }));
+        }
+    }
+
+    for suggestion in error.suggestions() {
+        let span = suggestion.span.origin();
+
+        related.push(json!({
+            "location": { "uri": span_uri(span, uri), "range": span_range(span) },
+            "message": format!("{}: `{}`", suggestion.msg, suggestion.replacement),
+        }));
+    }
+
+    let mut diagnostic = json!({
+        "range": range,
+        "severity": error.severity().lsp(),
+        "source": "lasagna",
+        "message": message,
+        "relatedInformation": related,
+    });
+
+    // the code is optional in LSP, unlike rustc's format it's left out rather than null
+    if let Some(code) = error.code() {
+        diagnostic["code"] = json!(code);
+    }
+
+    diagnostic
+}
+
+/// Converts a [`Symbol`] to an LSP document symbol.
+pub fn document_symbol(symbol: &Symbol) -> Value {
+    json!({
+        "name": symbol.name,
+        "kind": symbol_kind(symbol.kind),
+        "range": span_range(symbol.span),
+        "selectionRange": span_range(symbol.name_span),
+        "children": symbol.children.iter().map(document_symbol).collect::<Vec<_>>(),
+    })
+}
+
+/// Maps a symbol kind given in `#[parse(symbol = "...")]` to an LSP `SymbolKind`.
+pub fn symbol_kind(kind: &str) -> u32 {
+    match kind {
+        "file" => 1,
+        "module" => 2,
+        "namespace" => 3,
+        "package" => 4,
+        "class" => 5,
+        "method" => 6,
+        "property" => 7,
+        "field" => 8,
+        "constructor" => 9,
+        "enum" => 10,
+        "interface" => 11,
+        "function" => 12,
+        "variable" => 13,
+        "constant" => 14,
+        "string" => 15,
+        "number" => 16,
+        "boolean" => 17,
+        "array" => 18,
+        "object" => 19,
+        "key" => 20,
+        "null" => 21,
+        "enum_member" => 22,
+        "struct" => 23,
+        "event" => 24,
+        "operator" => 25,
+        "type_parameter" => 26,
+        _ => 13,
+    }
+}
+
+/// Reads a message framed with a `Content-Length` header, returns `None` at the end of input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn write_notification(output: &mut impl Write, method: &str, params: Value) -> io::Result<()> {
+    let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+
+    write_message(output, &message)
+}
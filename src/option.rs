@@ -0,0 +1,59 @@
+use crate::{
+    AstDump, Error, Generator, Grammar, GrammarExpr, Parse, ParseFirst, Parser, Printer, Symbol,
+};
+
+/// Parses `T` if it is next, see [`SpannedOption`](crate::SpannedOption) for a version with a
+/// span when empty.
+impl<T: Parse> Parse for Option<T> {
+    type Token = T::Token;
+
+    const FIRST: ParseFirst<Self::Token> = T::FIRST;
+
+    const NULLABLE: bool = true;
+
+    #[inline]
+    fn parse(parser: &mut impl Parser<Self::Token>) -> Result<Self, Error> {
+        parser.try_parse::<T>()
+    }
+
+    #[inline]
+    fn symbols(&self, symbols: &mut Vec<Symbol>) {
+        if let Some(value) = self {
+            value.symbols(symbols);
+        }
+    }
+
+    #[inline]
+    fn dump(&self, dump: &mut AstDump) {
+        match self {
+            Some(value) => value.dump(dump),
+            None => dump.leaf("None"),
+        }
+    }
+
+    #[inline]
+    fn print(&self, printer: &mut Printer) {
+        if let Some(value) = self {
+            value.print(printer);
+        }
+    }
+
+    #[inline]
+    fn generate(generator: &mut Generator) {
+        if generator.optional() {
+            T::generate(generator);
+        } else {
+            generator.leaf("None");
+        }
+    }
+
+    #[inline]
+    fn grammar(grammar: &mut Grammar) -> GrammarExpr {
+        GrammarExpr::optional(T::grammar(grammar))
+    }
+
+    #[inline]
+    fn is_next(parser: &mut impl Parser<Self::Token>) -> Option<bool> {
+        T::is_next(parser)
+    }
+}
@@ -1,10 +1,26 @@
-use crate::{Error, Lexer, Span, Token, TokenKind};
+use std::fmt::Display;
+
+use crate::{
+    error::expected_msg, short_type_name, AstDump, CharsLexer, Error, ErrorHint, Errors, Generator,
+    Grammar, GrammarExpr, Lexer, Printer, Severity, SourcePath, Span, SpannedToken, Symbol, Token,
+    TokenKind, TokenSet,
+};
 
 pub type ParseStart<T> = &'static StartTokens<'static, <T as Token>::Kind>;
 
+/// The kinds of tokens a [`Parse`] can start with, see [`Parse::FIRST`].
+pub type ParseFirst<T> = TokenSet<<T as Token>::Kind>;
+
+/// The tokens a [`Parse`] can start with, kept so that hand written implementations can still
+/// compose starts.
+///
+/// The parser itself only uses [`Parse::FIRST`], which derived impls compute at compile time.
+/// The deprecated [`Parse::START`] defaults to a [`StartTokens::Set`] of it.
 #[derive(Clone, Copy, Debug)]
 pub enum StartTokens<'a, T: TokenKind> {
     All,
+    /// A flattened set of kinds, checked with a single lookup.
+    Set(TokenSet<T>),
     Any(&'a [&'a StartTokens<'a, T>]),
     One(&'a StartTokens<'a, T>),
     Token(&'a T),
@@ -14,6 +30,7 @@ impl<'a, T: TokenKind> StartTokens<'a, T> {
     pub fn contains(&self, kind: &T) -> bool {
         match self {
             Self::All => true,
+            Self::Set(set) => set.contains(kind),
             &Self::Any(kinds) => {
                 for tok in kinds {
                     if tok.contains(kind) {
@@ -28,53 +45,223 @@ impl<'a, T: TokenKind> StartTokens<'a, T> {
         }
     }
 
-    pub fn append_vec(&self, vec: &mut Vec<&'a T>) {
-        match self {
-            &Self::Any(kinds) => {
-                for kind in kinds {
-                    kind.append_vec(vec);
-                }
-            }
-            &Self::One(kind) => kind.append_vec(vec),
-            &Self::Token(kind) => {
-                if !vec.contains(&kind) {
-                    vec.push(kind);
-                }
-            }
-            _ => {}
+    /// Returns whether the next token of `parser` is in `self`, `None` if unknown.
+    pub fn is_next<K>(&self, parser: &mut impl Parser<K>) -> Option<bool>
+    where
+        K: Token<Kind = T>,
+    {
+        match parser.peek() {
+            Ok(Some(token)) => Some(self.contains(&token.kind())),
+            Ok(None) => Some(false),
+            Err(_) => None,
         }
     }
 
-    pub fn to_vec(&self) -> Vec<&'a T> {
-        let mut vec = Vec::new();
-        self.append_vec(&mut vec);
-        vec
+    /// Flattens `self` into a [`TokenSet`].
+    pub fn to_set(&self) -> TokenSet<T> {
+        match *self {
+            Self::All => TokenSet::FULL,
+            Self::Set(set) => set,
+            Self::Any(kinds) => kinds
+                .iter()
+                .fold(TokenSet::EMPTY, |set, kind| set | kind.to_set()),
+            Self::One(kind) => kind.to_set(),
+            Self::Token(&kind) => TokenSet::EMPTY.with(kind.index()),
+        }
+    }
+
+    /// Returns the kinds in `self`, in index order.
+    ///
+    /// Starts containing [`StartTokens::All`] are empty here, since they don't expect any
+    /// particular token.
+    pub fn to_vec(&self) -> Vec<T> {
+        let set = self.to_set();
+
+        if set == TokenSet::FULL {
+            return Vec::new();
+        }
+
+        set.iter().collect()
     }
 }
 
 pub trait Parse: Sized {
     type Token: Token;
 
-    const START: ParseStart<Self::Token>;
+    /// The kinds of tokens `Self` can start with.
+    ///
+    /// The `FIRST` of a sequence is the union of the `FIRST` of its items up to and including
+    /// the first item that isn't [`NULLABLE`](Parse::NULLABLE). Sequences read the constants of
+    /// their items through [`first_of`] and [`nullable_of`], so that the items after the first
+    /// non-nullable one are never evaluated, which would form a cycle in recursive types.
+    const FIRST: ParseFirst<Self::Token>;
+
+    /// Whether `Self` can be parsed without consuming any tokens.
+    const NULLABLE: bool = false;
+
+    /// [`Parse::FIRST`] as [`StartTokens`].
+    ///
+    /// Nothing reads this anymore, implement [`Parse::FIRST`] instead.
+    #[deprecated(note = "implement `Parse::FIRST` instead, the parser no longer reads `START`")]
+    const START: ParseStart<Self::Token> = &StartTokens::Set(Self::FIRST);
 
     fn parse(parser: &mut impl Parser<Self::Token>) -> Result<Self, Error>;
 
+    /// Appends the [`Symbol`]s in `self` to `symbols`.
+    #[allow(unused)]
+    #[inline]
+    fn symbols(&self, symbols: &mut Vec<Symbol>) {}
+
+    /// Writes `self` to `dump`, see [`AstDump`].
+    ///
+    /// Types that don't override this are a leaf named by [`short_type_name`].
+    #[inline]
+    fn dump(&self, dump: &mut AstDump) {
+        dump.leaf(short_type_name::<Self>());
+    }
+
+    /// Prints `self` back to source, see [`Printer`].
+    ///
+    /// Types that don't override this print nothing.
+    #[allow(unused)]
+    #[inline]
+    fn print(&self, printer: &mut Printer) {}
+
+    /// Generates the source of a random `Self`, see [`Generator`].
+    ///
+    /// Types that don't override this fail generation.
+    #[inline]
+    fn generate(generator: &mut Generator) {
+        generator.fail(format!("cannot generate {}", std::any::type_name::<Self>()));
+    }
+
+    /// Returns the grammar expression of `Self`, adding the rules it refers to to `grammar`.
+    ///
+    /// Types that don't override this refer to a rule named by [`short_type_name`].
     #[allow(unused)]
+    #[inline]
+    fn grammar(grammar: &mut Grammar) -> GrammarExpr {
+        GrammarExpr::NonTerminal(short_type_name::<Self>())
+    }
+
+    /// Returns whether `Self` can be parsed from the next token, `None` if unknown.
     fn is_next(parser: &mut impl Parser<Self::Token>) -> Option<bool> {
-        Some(Self::START.contains(&parser.peek().ok()??.kind()))
+        Self::FIRST.is_next(parser)
     }
 }
 
+/// Returns [`Parse::FIRST`] of `P`.
+///
+/// Naming a constant evaluates it even in a branch that isn't taken, calling this function
+/// only evaluates it if the call is reached.
+#[inline]
+pub const fn first_of<P: Parse>() -> ParseFirst<P::Token> {
+    P::FIRST
+}
+
+/// Returns [`Parse::NULLABLE`] of `P`, see [`first_of`].
+#[inline]
+pub const fn nullable_of<P: Parse>() -> bool {
+    P::NULLABLE
+}
+
 pub trait Parser<T> {
+    /// Saved parser state, see [`Parser::checkpoint`].
+    type Checkpoint;
+
+    /// Returns a [`Span`] at the start of the next token.
     fn span(&mut self, length: usize) -> Span;
 
-    fn next(&mut self) -> Result<T, Error>;
+    /// Returns the [`Span`] of the last token returned by [`Parser::next`], or an empty span
+    /// at the start of the input if no token has been consumed yet.
+    fn prev_span(&self) -> Span;
+
+    fn next(&mut self) -> Result<SpannedToken<T>, Error>;
 
-    fn peek(&mut self) -> Result<Option<&T>, Error>;
+    /// Peeks the next token, returns `None` at the end of input.
+    fn peek(&mut self) -> Result<Option<&SpannedToken<T>>, Error>;
 
     fn is_empty(&mut self) -> bool;
 
-    fn fork(&mut self) -> Self;
+    /// Saves the current position of the parser, which can later be restored with
+    /// [`Parser::rewind`].
+    fn checkpoint(&self) -> Self::Checkpoint;
+
+    /// Restores the parser to a position saved by [`Parser::checkpoint`].
+    ///
+    /// Everything recorded since the checkpoint is undone, including [`Parser::prev_span`], the
+    /// tokens added with [`Parser::add_expected`] and the reported diagnostics.
+    fn rewind(&mut self, checkpoint: Self::Checkpoint);
+
+    /// Runs `f`, rewinding the parser if it returns an error.
+    #[inline]
+    fn attempt<R>(&mut self, f: impl FnOnce(&mut Self) -> Result<R, Error>) -> Result<R, Error>
+    where
+        Self: Sized,
+    {
+        let checkpoint = self.checkpoint();
+        let result = f(self);
+
+        if let Err(ref _error) = result {
+            self.rewind(checkpoint);
+
+            #[cfg(feature = "trace")]
+            crate::trace::backtrack(self.span(0), _error);
+        }
+
+        result
+    }
+
+    /// Enters a nested node, returns an error if the maximum nesting depth is exceeded.
+    ///
+    /// Every call is paired with a call to [`Parser::exit`], see [`Parser::nested`].
+    #[inline]
+    fn enter(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Exits a node entered with [`Parser::enter`].
+    #[inline]
+    fn exit(&mut self) {}
+
+    /// Returns whether a lexer or repetition that makes no progress panics instead of returning
+    /// an error.
+    ///
+    /// No progress is always a bug in the grammar, so by default it panics in debug builds.
+    #[inline]
+    fn panics_on_no_progress(&self) -> bool {
+        cfg!(debug_assertions)
+    }
+
+    /// Returns whether [`Parser::nested`] grows the stack instead of limiting the depth.
+    #[inline]
+    fn grows_stack(&self) -> bool {
+        false
+    }
+
+    /// Runs `f` one nesting level deeper, used by derived [`Parse`] impls to limit recursion
+    /// on deeply nested input.
+    #[inline]
+    fn nested<R>(&mut self, f: impl FnOnce(&mut Self) -> Result<R, Error>) -> Result<R, Error>
+    where
+        Self: Sized,
+    {
+        self.enter()?;
+
+        #[cfg(feature = "grow-stack")]
+        let result = if self.grows_stack() {
+            stacker::maybe_grow(STACK_RED_ZONE, STACK_GROWTH, || f(self))
+        } else {
+            f(self)
+        };
+
+        #[cfg(not(feature = "grow-stack"))]
+        let result = f(self);
+
+        self.exit();
+
+        result
+    }
 
     fn parse<P: Parse<Token = T>>(&mut self) -> Result<P, Error>
     where
@@ -83,37 +270,207 @@ pub trait Parser<T> {
         P::parse(self)
     }
 
+    /// Records that a token in `expected` could have been at `span`, so that an error there
+    /// can list every alternative, see [`Parser::expected_at`].
+    ///
+    /// Parsers that don't track expectations ignore this.
+    #[allow(unused)]
+    #[inline]
+    fn add_expected(&mut self, span: Span, expected: TokenSet<T::Kind>)
+    where
+        T: Token,
+    {
+    }
+
+    /// Returns the tokens recorded with [`Parser::add_expected`] at `span`.
+    #[allow(unused)]
+    #[inline]
+    fn expected_at(&self, span: Span) -> TokenSet<T::Kind>
+    where
+        T: Token,
+    {
+        TokenSet::EMPTY
+    }
+
+    /// Reports a non-fatal diagnostic, like a warning for a deprecated construct.
+    ///
+    /// Diagnostics emitted inside an [`Parser::attempt`] that fails are discarded. Parsers
+    /// that don't collect diagnostics ignore this.
+    #[allow(unused)]
+    #[inline]
+    fn diagnostic(&mut self, diagnostic: Error) {}
+
+    /// Reports a warning at `span`, see [`Parser::diagnostic`].
+    #[track_caller]
+    #[inline]
+    fn warn(&mut self, span: Span, msg: impl Display)
+    where
+        Self: Sized,
+    {
+        self.diagnostic(Error::spanned(span, msg).with_severity(Severity::Warning));
+    }
+
+    /// Takes the diagnostics reported so far, see [`Parser::diagnostic`].
+    #[inline]
+    fn take_diagnostics(&mut self) -> Errors {
+        Errors::new()
+    }
+
+    /// Creates the error for `P` not starting at `span`, where the token `found` is.
+    ///
+    /// The error expects the start of `P` together with every other token expected at `span`.
+    fn expected_error<P: Parse<Token = T>>(&mut self, span: Span, found: impl Display) -> Error
+    where
+        Self: Sized,
+        T: Token,
+    {
+        self.add_expected(span, P::FIRST);
+
+        let expected = P::FIRST | self.expected_at(span);
+
+        Error::expected_one(span, expected, found)
+    }
+
     fn try_parse<P: Parse<Token = T>>(&mut self) -> Result<Option<P>, Error>
     where
         Self: Sized,
+        T: Token,
     {
-        match P::is_next(self) {
-            Some(true) => Ok(Some(P::parse(self)?)),
-            Some(false) => Ok(None),
-            None => {
-                let mut fork = self.fork();
+        let next = P::is_next(self);
 
-                let parse = P::parse(&mut fork)?;
+        #[cfg(feature = "trace")]
+        crate::trace::try_parse(std::any::type_name::<P>(), self.span(0), next);
 
-                *self = fork;
+        match next {
+            Some(true) => Ok(Some(P::parse(self)?)),
+            Some(false) => {
+                let span = self.span(0);
+                self.add_expected(span, P::FIRST);
 
-                Ok(Some(parse))
+                Ok(None)
             }
+            None => self.attempt(|parser| P::parse(parser)).map(Some),
         }
     }
 }
 
+/// Default maximum nesting depth of [`SkipWhitespace`].
+///
+/// The depth counts the derived nodes being parsed at once, not levels of recursion: a
+/// grammar where each level of parentheses goes through five derived types reaches the
+/// default at about a hundred parentheses. It is chosen so that the deepest input fits in the
+/// 2 MiB stack of a spawned thread in a debug build.
+pub const DEFAULT_MAX_DEPTH: usize = 512;
+
+/// Remaining stack below which [`Parser::nested`] allocates a new stack segment.
+#[cfg(feature = "grow-stack")]
+const STACK_RED_ZONE: usize = 64 * 1024;
+
+/// Size of the stack segments allocated by [`Parser::nested`].
+#[cfg(feature = "grow-stack")]
+const STACK_GROWTH: usize = 1024 * 1024;
+
 #[derive(Clone, Debug)]
-pub struct SkipWhitespace<L, T> {
-    lexer: L,
-    peek: Option<T>,
+struct Peeked<C, T> {
+    /// Lexer position before the token, whitespace already skipped.
+    checkpoint: C,
+    token: SpannedToken<T>,
+}
+
+/// Tokens expected at the furthest offset reached, see [`Parser::add_expected`].
+///
+/// The kinds are stored as indices, since the parser doesn't require its token to be a
+/// [`Token`].
+#[derive(Clone, Copy, Default)]
+struct Expected {
+    offset: usize,
+    indices: TokenSet<()>,
+}
+
+impl std::fmt::Debug for Expected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Expected")
+            .field("offset", &self.offset)
+            .field("indices", &self.indices.indices().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Saved state of a [`SkipWhitespace`], see [`Parser::checkpoint`].
+#[derive(Clone, Debug)]
+pub struct SkipWhitespaceCheckpoint<C> {
+    lexer: C,
+    prev_span: Span,
+    expected: Expected,
+    diagnostics: usize,
 }
 
 /// [`Parser`] that skips whitespace between tokens.
-impl<L, T> SkipWhitespace<L, T> {
+///
+/// Every token is lexed after skipping the whitespace preceding it, so the span of a token
+/// covers exactly the characters of that token.
+#[derive(Clone, Debug)]
+pub struct SkipWhitespace<L: Lexer, T> {
+    lexer: L,
+    peek: Option<Peeked<L::Checkpoint, T>>,
+    prev_span: Span,
+    expected: Expected,
+    /// Warnings emitted so far, in order, so that rewinding can drop the ones after a
+    /// checkpoint.
+    diagnostics: Vec<Error>,
+    depth: usize,
+    max_depth: usize,
+    grow_stack: bool,
+    no_progress_panics: bool,
+}
+
+impl<L: Lexer, T> SkipWhitespace<L, T> {
+    #[inline]
+    pub fn new(mut lexer: L) -> Self {
+        let prev_span = lexer.span(0);
+
+        Self {
+            lexer,
+            peek: None,
+            prev_span,
+            expected: Expected::default(),
+            diagnostics: Vec::new(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            grow_stack: false,
+            no_progress_panics: cfg!(debug_assertions),
+        }
+    }
+
+    /// Sets whether no progress panics, see [`Parser::panics_on_no_progress`].
+    ///
+    /// Turning the panic off in debug builds is meant for inputs where the error is expected,
+    /// like when fuzzing.
     #[inline]
-    pub fn new(lexer: L) -> Self {
-        Self { lexer, peek: None }
+    pub fn with_no_progress_panics(mut self, panics: bool) -> Self {
+        self.no_progress_panics = panics;
+        self
+    }
+
+    /// Sets the maximum nesting depth, see [`Parser::nested`] and [`DEFAULT_MAX_DEPTH`].
+    ///
+    /// Every derived node being parsed counts towards the depth. Deeper input fails with a
+    /// "nesting too deep" error instead of overflowing the stack.
+    #[inline]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Grows the stack on demand instead of limiting the nesting depth.
+    ///
+    /// Memory use is then only bounded by the input, so this is meant for trusted input.
+    #[cfg(feature = "grow-stack")]
+    #[inline]
+    pub fn with_stack_growth(mut self) -> Self {
+        self.grow_stack = true;
+        self.max_depth = usize::MAX;
+        self
     }
 
     #[inline]
@@ -130,6 +487,36 @@ impl<L, T> SkipWhitespace<L, T> {
             self.lexer.consume();
         }
     }
+
+    #[inline]
+    fn lex(&mut self) -> Result<Peeked<L::Checkpoint, T>, Error>
+    where
+        L: Lexer<Output = char>,
+        T: Token<char>,
+    {
+        self.skip_whitespace();
+
+        let checkpoint = self.lexer.checkpoint();
+        let start = self.lexer.span(0);
+        let token = T::lex(&mut self.lexer)?;
+        let span = start | self.lexer.span(0);
+
+        if span.length == 0 {
+            return Err(no_progress(
+                self.no_progress_panics,
+                start,
+                format!(
+                    "'{}::lex' returned a token without consuming input",
+                    std::any::type_name::<T>()
+                ),
+            ));
+        }
+
+        Ok(Peeked {
+            checkpoint,
+            token: SpannedToken::new(span, token),
+        })
+    }
 }
 
 impl<L, T> Parser<T> for SkipWhitespace<L, T>
@@ -137,39 +524,246 @@ where
     L: Lexer<Output = char>,
     T: Token<char>,
 {
+    type Checkpoint = SkipWhitespaceCheckpoint<L::Checkpoint>;
+
     fn span(&mut self, length: usize) -> Span {
+        if let Some(ref peek) = self.peek {
+            return Span {
+                length,
+                ..peek.token.span
+            };
+        }
+
         self.skip_whitespace();
 
         self.lexer.span(length)
     }
 
-    fn next(&mut self) -> Result<T, Error> {
-        if let Some(peek) = self.peek.take() {
-            Ok(peek)
-        } else {
-            T::lex(&mut self.lexer)
-        }
+    #[inline]
+    fn prev_span(&self) -> Span {
+        self.prev_span
     }
 
-    fn peek(&mut self) -> Result<Option<&T>, Error> {
-        if let Some(ref token) = self.peek {
-            Ok(Some(token))
-        } else {
-            self.skip_whitespace();
+    fn next(&mut self) -> Result<SpannedToken<T>, Error> {
+        let peeked = match self.peek.take() {
+            Some(peeked) => peeked,
+            None => self.lex()?,
+        };
 
-            self.peek = Some(T::lex(&mut self.lexer)?);
+        self.prev_span = peeked.token.span;
+
+        Ok(peeked.token)
+    }
 
-            Ok(self.peek.as_ref())
+    fn peek(&mut self) -> Result<Option<&SpannedToken<T>>, Error> {
+        if self.peek.is_none() {
+            if self.is_empty() {
+                return Ok(None);
+            }
+
+            self.peek = Some(self.lex()?);
         }
+
+        Ok(self.peek.as_ref().map(|peek| &peek.token))
     }
 
     fn is_empty(&mut self) -> bool {
+        if self.peek.is_some() {
+            return false;
+        }
+
         self.skip_whitespace();
 
         self.lexer.is_empty()
     }
 
-    fn fork(&mut self) -> Self {
-        Self::new(self.lexer.fork())
+    fn enter(&mut self) -> Result<(), Error> {
+        if self.depth >= self.max_depth {
+            let span = self.span(0);
+
+            return Err(
+                Error::spanned(span, "nesting too deep").with_hint(ErrorHint::new(format!(
+                    "the maximum nesting depth is {}",
+                    self.max_depth
+                ))),
+            );
+        }
+
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    #[inline]
+    fn exit(&mut self) {
+        self.depth -= 1;
+    }
+
+    #[inline]
+    fn panics_on_no_progress(&self) -> bool {
+        self.no_progress_panics
+    }
+
+    #[inline]
+    fn grows_stack(&self) -> bool {
+        self.grow_stack
+    }
+
+    fn add_expected(&mut self, span: Span, expected: TokenSet<T::Kind>) {
+        let indices = expected.cast();
+
+        if span.offset > self.expected.offset {
+            self.expected = Expected {
+                offset: span.offset,
+                indices,
+            };
+        } else if span.offset == self.expected.offset {
+            self.expected.indices = self.expected.indices | indices;
+        }
+    }
+
+    #[inline]
+    fn expected_at(&self, span: Span) -> TokenSet<T::Kind> {
+        if span.offset == self.expected.offset {
+            self.expected.indices.cast()
+        } else {
+            TokenSet::EMPTY
+        }
+    }
+
+    fn checkpoint(&self) -> Self::Checkpoint {
+        let lexer = match self.peek {
+            Some(ref peek) => peek.checkpoint.clone(),
+            None => self.lexer.checkpoint(),
+        };
+
+        SkipWhitespaceCheckpoint {
+            lexer,
+            prev_span: self.prev_span,
+            expected: self.expected,
+            diagnostics: self.diagnostics.len(),
+        }
+    }
+
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        self.peek = None;
+        self.prev_span = checkpoint.prev_span;
+        self.expected = checkpoint.expected;
+        self.diagnostics.truncate(checkpoint.diagnostics);
+        self.lexer.rewind(checkpoint.lexer);
+    }
+
+    #[inline]
+    fn diagnostic(&mut self, diagnostic: Error) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    #[inline]
+    fn take_diagnostics(&mut self) -> Errors {
+        self.diagnostics.drain(..).collect()
+    }
+}
+
+/// Creates the error for a lexer or parser that didn't consume input, which would otherwise
+/// loop forever.
+///
+/// Panics with the error instead if `panics`, see [`Parser::panics_on_no_progress`].
+#[track_caller]
+pub(crate) fn no_progress(panics: bool, span: Span, msg: impl Display) -> Error {
+    let error = Error::spanned(span, msg).with_hint(ErrorHint::new(
+        "this is a bug in the grammar, it would loop forever",
+    ));
+
+    if panics {
+        panic!("{}", error);
     }
+
+    error
+}
+
+/// Parses all of `source` as `P`, skipping whitespace between tokens.
+///
+/// Returns an error if any tokens are left after `P`.
+pub fn parse_source<P>(source: &str, path: SourcePath) -> Result<P, Error>
+where
+    P: Parse,
+    P::Token: Token<char>,
+{
+    parse_all(&mut SkipWhitespace::new(CharsLexer::new(source, path)))
+}
+
+/// Parses the rest of the input of `parser` as `P`, failing if any tokens are left.
+fn parse_all<P, L>(parser: &mut SkipWhitespace<L, P::Token>) -> Result<P, Error>
+where
+    P: Parse,
+    P::Token: Token<char>,
+    L: Lexer<Output = char>,
+{
+    let node = parser.parse::<P>()?;
+
+    if let Some(token) = parser.peek()? {
+        let (span, found) = (token.span, token.kind().label());
+
+        return Err(expected_eof(span, parser.expected_at(span), found));
+    }
+
+    Ok(node)
+}
+
+/// A node parsed by [`parse_source_with_diagnostics`], with the warnings reported while
+/// parsing it.
+#[derive(Debug)]
+pub struct Parsed<P> {
+    pub node: P,
+    pub diagnostics: Errors,
+}
+
+/// Parses all of `source` as `P` like [`parse_source`], also returning the diagnostics reported
+/// while parsing.
+///
+/// On failure the error is returned together with the diagnostics reported before it.
+pub fn parse_source_with_diagnostics<P>(source: &str, path: SourcePath) -> Result<Parsed<P>, Errors>
+where
+    P: Parse,
+    P::Token: Token<char>,
+{
+    parse_lexer_with_diagnostics(CharsLexer::new(source, path))
+}
+
+/// Parses the rest of `lexer` as `P` like [`parse_source_with_diagnostics`].
+pub(crate) fn parse_lexer_with_diagnostics<P>(
+    lexer: impl Lexer<Output = char>,
+) -> Result<Parsed<P>, Errors>
+where
+    P: Parse,
+    P::Token: Token<char>,
+{
+    let mut parser = SkipWhitespace::new(lexer);
+    let result = parse_all::<P, _>(&mut parser);
+    let mut diagnostics = parser.take_diagnostics();
+
+    match result {
+        Ok(node) => Ok(Parsed { node, diagnostics }),
+        Err(error) => {
+            diagnostics.push(error);
+
+            Err(diagnostics)
+        }
+    }
+}
+
+/// Creates the error for a token at `span` after the end of the input was expected, `expected`
+/// are the tokens that could also have been next.
+pub(crate) fn expected_eof<K: TokenKind>(
+    span: Span,
+    expected: TokenSet<K>,
+    found: impl Display,
+) -> Error {
+    let msg = if expected.is_empty() || expected == TokenSet::FULL {
+        String::from("expected eof")
+    } else {
+        format!("{} or eof", expected_msg(expected, &found))
+    };
+
+    Error::spanned(span, msg).with_hint(ErrorHint::spanned(span, format!("found {}", found)))
 }
@@ -0,0 +1,42 @@
+use crate::Parse;
+
+/// Writer that prints syntax trees back to source, see [`Parse::print`].
+///
+/// Derived tokens print the text they are declared with and extern tokens print the text
+/// they were lexed from, so the output depends only on the tree. Tokens are separated by a
+/// single space, so the output is not formatted like the original source but lexes to the
+/// same tokens.
+#[derive(Clone, Debug, Default)]
+pub struct Printer {
+    out: String,
+}
+
+impl Printer {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes the text of a token.
+    #[inline]
+    pub fn token(&mut self, text: &str) {
+        if !self.out.is_empty() {
+            self.out.push(' ');
+        }
+
+        self.out.push_str(text);
+    }
+
+    #[inline]
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+/// Prints `node` back to source, see [`Printer`].
+#[inline]
+pub fn print_ast(node: &impl Parse) -> String {
+    let mut printer = Printer::new();
+    node.print(&mut printer);
+    printer.finish()
+}
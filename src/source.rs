@@ -0,0 +1,221 @@
+use std::{
+    borrow::Cow,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use dashmap::DashMap;
+
+use crate::{CharsLexer, SourcePath};
+
+lazy_static::lazy_static! {
+    static ref SHARED_SOURCES: DashMap<usize, Arc<str>> = DashMap::new();
+}
+
+static NEXT_SHARED_SOURCE: AtomicUsize = AtomicUsize::new(0);
+
+/// Handle to the text a [`Span`](crate::Span) points into.
+///
+/// The text is either `'static` or owned by a [`SharedSource`]. Text of a shared source reads
+/// as empty once the [`SharedSource`] has been dropped.
+#[derive(Clone, Copy)]
+pub struct SourceText(Repr);
+
+#[derive(Clone, Copy)]
+enum Repr {
+    Static(&'static str),
+    Shared(usize),
+}
+
+impl SourceText {
+    #[inline]
+    pub const fn from_static(text: &'static str) -> Self {
+        Self(Repr::Static(text))
+    }
+
+    /// Returns the text, or an empty string if it's owned by a dropped [`SharedSource`].
+    #[inline]
+    pub fn get(&self) -> SourceRef {
+        match self.0 {
+            Repr::Static(text) => SourceRef::Static(text),
+            Repr::Shared(id) => match SHARED_SOURCES.get(&id) {
+                Some(text) => SourceRef::Shared(text.clone()),
+                None => SourceRef::Static(""),
+            },
+        }
+    }
+
+    /// Returns the text in `range`, borrowed if the text is `'static`.
+    ///
+    /// Returns an empty string if the text is gone or `range` is out of bounds.
+    #[inline]
+    pub fn slice(&self, range: std::ops::Range<usize>) -> Cow<'static, str> {
+        match self.get() {
+            SourceRef::Static(text) => Cow::Borrowed(text.get(range).unwrap_or_default()),
+            SourceRef::Shared(text) => Cow::Owned(text.get(range).unwrap_or_default().to_owned()),
+        }
+    }
+
+    #[inline]
+    fn key(&self) -> (usize, usize, usize) {
+        match self.0 {
+            Repr::Static(text) => (0, text.as_ptr() as usize, text.len()),
+            Repr::Shared(id) => (1, id, 0),
+        }
+    }
+}
+
+impl From<&'static str> for SourceText {
+    #[inline]
+    fn from(text: &'static str) -> Self {
+        Self::from_static(text)
+    }
+}
+
+/// Sources are equal if they are the same text, not if their contents are equal.
+impl PartialEq for SourceText {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for SourceText {}
+
+impl PartialOrd for SourceText {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SourceText {
+    #[inline]
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Hash for SourceText {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl std::fmt::Debug for SourceText {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Repr::Static(text) => f.debug_tuple("Static").field(&text.len()).finish(),
+            Repr::Shared(id) => f.debug_tuple("Shared").field(&id).finish(),
+        }
+    }
+}
+
+/// Text returned by [`SourceText::get`].
+#[derive(Clone, Debug)]
+pub enum SourceRef {
+    Static(&'static str),
+    Shared(Arc<str>),
+}
+
+impl Deref for SourceRef {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        match self {
+            Self::Static(text) => text,
+            Self::Shared(text) => text,
+        }
+    }
+}
+
+/// Source text owned by the caller rather than leaked.
+///
+/// Spans into a shared source stay valid after it's dropped, but their text reads as empty.
+/// Use this for sources that are replaced often, like documents edited in a language server.
+#[derive(Debug)]
+pub struct SharedSource {
+    id: usize,
+    text: Arc<str>,
+}
+
+impl SharedSource {
+    pub fn new(text: impl Into<Arc<str>>) -> Self {
+        let id = NEXT_SHARED_SOURCE.fetch_add(1, Ordering::Relaxed);
+        let text = text.into();
+
+        SHARED_SOURCES.insert(id, text.clone());
+
+        Self { id, text }
+    }
+
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the handle spans into this source refer to it by.
+    #[inline]
+    pub fn source_text(&self) -> SourceText {
+        SourceText(Repr::Shared(self.id))
+    }
+
+    /// Returns a lexer over the source.
+    #[inline]
+    pub fn lexer(&self, path: SourcePath) -> CharsLexer<'_> {
+        CharsLexer::with_source_text(&self.text, self.source_text(), path)
+    }
+}
+
+impl Drop for SharedSource {
+    #[inline]
+    fn drop(&mut self) {
+        SHARED_SOURCES.remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lexer;
+
+    #[test]
+    fn shared_source_is_freed_on_drop() {
+        let source = SharedSource::new("abc def");
+        let span = {
+            let mut lexer = source.lexer(SourcePath::Generated);
+            lexer.consume();
+            lexer.span(2)
+        };
+
+        assert_eq!(span.text(), "bc");
+        assert_eq!(SHARED_SOURCES.get(&source.id).unwrap().len(), 7);
+
+        let id = source.id;
+        drop(source);
+
+        assert!(SHARED_SOURCES.get(&id).is_none());
+        assert_eq!(span.text(), "");
+        assert_eq!(span.byte_range(), 0..0);
+    }
+
+    #[test]
+    fn sources_compare_by_identity() {
+        let a = SharedSource::new("a");
+        let b = SharedSource::new("a");
+
+        assert_eq!(a.source_text(), a.source_text());
+        assert_ne!(a.source_text(), b.source_text());
+        let text = "ab";
+        assert_ne!(
+            SourceText::from_static(text),
+            SourceText::from_static(&text[..1])
+        );
+    }
+}
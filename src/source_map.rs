@@ -0,0 +1,164 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{CharsLexer, Error, SourcePath, Span};
+
+/// Collection of the sources that make up a program.
+///
+/// Sources are leaked so that [`Span`]s into them are `'static`, loading the same path twice
+/// returns the already loaded source.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    sources: HashMap<SourcePath, &'static str>,
+}
+
+impl SourceMap {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an in-memory source and returns a lexer for it.
+    pub fn add(&mut self, path: SourcePath, source: impl Into<String>) -> CharsLexer<'static> {
+        let source: &'static str = Box::leak(source.into().into_boxed_str());
+        self.sources.insert(path, source);
+
+        CharsLexer::new_static(source, path)
+    }
+
+    /// Reads the file at `path`, registers it and returns a lexer for it.
+    ///
+    /// `path` is normalized first, so `a/../b` and `./b` load the same source as `b`.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<CharsLexer<'static>> {
+        let path = normalize(path.as_ref());
+        let source_path = SourcePath::path(&path);
+
+        if let Some(source) = self.sources.get(&source_path) {
+            return Ok(CharsLexer::new_static(source, source_path));
+        }
+
+        let source = fs::read_to_string(path)?;
+
+        Ok(self.add(source_path, source))
+    }
+
+    /// Loads a file named by an include directive at `from`.
+    ///
+    /// Relative paths are resolved against the directory of the file containing `from`. Returns
+    /// `None` if the file has already been loaded, so that a file is only included once and
+    /// cyclic includes terminate.
+    pub fn include(
+        &mut self,
+        from: Span,
+        path: impl AsRef<Path>,
+    ) -> Result<Option<CharsLexer<'static>>, Error> {
+        let path = Self::resolve(from.path, path.as_ref());
+
+        if self.contains(SourcePath::path(&path)) {
+            return Ok(None);
+        }
+
+        match self.load(&path) {
+            Ok(lexer) => Ok(Some(lexer)),
+            Err(err) => Err(Error::spanned(
+                from,
+                format!("failed to include '{}'", path.display()),
+            )
+            .with_source(err)),
+        }
+    }
+
+    /// Resolves `path` relative to the directory containing `from` and normalizes it.
+    pub fn resolve(from: SourcePath, path: &Path) -> PathBuf {
+        match from {
+            SourcePath::Path(from) if path.is_relative() => match from.parent() {
+                Some(dir) => normalize(&dir.join(path)),
+                None => normalize(path),
+            },
+            _ => normalize(path),
+        }
+    }
+
+    #[inline]
+    pub fn contains(&self, path: SourcePath) -> bool {
+        self.sources.contains_key(&path)
+    }
+
+    #[inline]
+    pub fn get(&self, path: SourcePath) -> Option<&'static str> {
+        self.sources.get(&path).copied()
+    }
+
+    /// Iterates over the paths of all registered sources.
+    #[inline]
+    pub fn paths(&self) -> impl Iterator<Item = SourcePath> + '_ {
+        self.sources.keys().copied()
+    }
+}
+
+/// Removes `.` components and folds `..` into the preceding component without touching the
+/// file system.
+///
+/// Leading `..` of relative paths are kept, `..` at the root is dropped.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lexer;
+
+    #[test]
+    fn normalize_folds_dots() {
+        assert_eq!(normalize(Path::new("a/./b/../c")), Path::new("a/c"));
+        assert_eq!(normalize(Path::new("../a/../../b")), Path::new("../../b"));
+        assert_eq!(normalize(Path::new("/../a")), Path::new("/a"));
+    }
+
+    #[test]
+    fn cyclic_includes_terminate() {
+        let dir = std::env::temp_dir().join(format!("lasagna-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("main.txt"), "main").unwrap();
+        fs::write(dir.join("sub/other.txt"), "other").unwrap();
+
+        let mut sources = SourceMap::new();
+        let main = sources.load(dir.join("main.txt")).unwrap().span(0);
+
+        let other = sources
+            .include(main, "sub/../sub/./other.txt")
+            .unwrap()
+            .unwrap()
+            .span(0);
+
+        assert!(sources.include(other, "../main.txt").unwrap().is_none());
+        assert!(sources
+            .include(other, "../sub/other.txt")
+            .unwrap()
+            .is_none());
+        assert!(sources.include(main, "./sub/other.txt").unwrap().is_none());
+        assert_eq!(sources.paths().count(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,21 +1,68 @@
 use std::{
-    ops::{BitOr, BitOrAssign, Deref, DerefMut},
+    borrow::Cow,
+    ops::{BitOr, BitOrAssign, Deref, DerefMut, Range},
     path::Path,
 };
 
-use crate::{string_allocator::static_str, Error, Parse, ParseStart, Parser};
+use crate::{
+    string_allocator::{static_expansion, static_path, static_str},
+    AstDump, Error, Generator, Grammar, GrammarExpr, Parse, ParseFirst, Parser, Printer,
+    SourceText, Symbol,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum SourcePath {
     Path(&'static Path),
     Generated,
+    /// Source generated by expanding something at a call site.
+    Expansion(&'static Expansion),
     Other(&'static str),
 }
 
 impl SourcePath {
-    pub fn path(path: &Path) -> Self {
-        let string = static_str(path.as_os_str().to_str().unwrap());
-        Self::Path(Path::new(string))
+    #[inline]
+    pub fn path(path: impl AsRef<Path>) -> Self {
+        Self::Path(static_path(path.as_ref()))
+    }
+
+    /// Creates the path of a source generated by expanding `name` at `call_site`.
+    ///
+    /// Expansions are interned like paths: expanding the same name at the same call site again
+    /// returns the same path, and each distinct expansion is kept for the rest of the program.
+    #[inline]
+    pub fn expansion(name: impl AsRef<str>, call_site: Span) -> Self {
+        let name = static_str(name.as_ref());
+
+        Self::Expansion(static_expansion(Expansion { name, call_site }))
+    }
+
+    #[inline]
+    pub fn as_expansion(&self) -> Option<&'static Expansion> {
+        match *self {
+            Self::Expansion(expansion) => Some(expansion),
+            _ => None,
+        }
+    }
+}
+
+/// Provenance of a generated source, see [`SourcePath::expansion`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Expansion {
+    /// Name of what was expanded, e.g. a macro.
+    pub name: &'static str,
+    /// Span the source was expanded at.
+    pub call_site: Span,
+}
+
+impl std::fmt::Display for SourcePath {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Path(path) => write!(f, "{}", path.display()),
+            Self::Generated => write!(f, "<generated>"),
+            Self::Expansion(expansion) => write!(f, "<expansion of {}>", expansion.name),
+            Self::Other(name) => write!(f, "{}", name),
+        }
     }
 }
 
@@ -25,7 +72,7 @@ pub struct Span {
     /// Path to source.
     pub path: SourcePath,
     /// Source.
-    pub source: &'static str,
+    pub source: SourceText,
     /// Line in source.
     pub line: usize,
     /// Column in source.
@@ -34,25 +81,115 @@ pub struct Span {
     pub offset: usize,
     /// Length in characters.
     pub length: usize,
+    /// Byte offset of the start of the span in source.
+    pub byte_offset: usize,
 }
 
-impl BitOr for Span {
-    type Output = Self;
+impl Span {
+    /// Returns the byte range of the span in [`Span::source`].
+    ///
+    /// Runs in time proportional to the length of the span, not its offset.
+    pub fn byte_range(&self) -> Range<usize> {
+        let source = self.source.get();
+        let len = source.len();
+        let start = self.byte_offset.min(len);
+        let end = match self.length {
+            0 => start,
+            length => source[start..]
+                .char_indices()
+                .nth(length)
+                .map(|(i, _)| start + i)
+                .unwrap_or(len),
+        };
+
+        start..end
+    }
 
+    /// Returns the source text covered by the span.
     #[inline]
-    fn bitor(self, rhs: Self) -> Self::Output {
-        assert_eq!(self.path, rhs.path);
-        assert_eq!(self.source as *const _, rhs.source as *const _);
+    pub fn text(&self) -> Cow<'static, str> {
+        self.source.slice(self.byte_range())
+    }
+
+    /// Returns the line and column of the end of the span.
+    pub fn end(&self) -> (usize, usize) {
+        let text = self.text();
 
+        match text.rfind('\n') {
+            Some(i) => (
+                self.line + text.matches('\n').count(),
+                text[i + 1..].chars().count(),
+            ),
+            None => (self.line, self.column + self.length),
+        }
+    }
+}
+
+impl Span {
+    /// Returns `true` if `self` and `other` point into the same source.
+    #[inline]
+    pub fn same_source(&self, other: &Span) -> bool {
+        self.path == other.path && self.source == other.source
+    }
+
+    /// Returns the span that `self` was expanded at, if its source is an expansion.
+    #[inline]
+    pub fn call_site(&self) -> Option<Span> {
+        self.path
+            .as_expansion()
+            .map(|expansion| expansion.call_site)
+    }
+
+    /// Iterates over the expansions `self` is in, innermost first.
+    pub fn expansions(&self) -> impl Iterator<Item = &'static Expansion> {
+        let mut path = self.path;
+
+        std::iter::from_fn(move || {
+            let expansion = path.as_expansion()?;
+            path = expansion.call_site.path;
+            Some(expansion)
+        })
+    }
+
+    /// Returns the outermost call site of `self`, or `self` if it isn't in an expansion.
+    #[inline]
+    pub fn origin(&self) -> Span {
+        self.ancestors().last().unwrap_or(*self)
+    }
+
+    /// Returns `self` followed by the call sites it was expanded from, innermost first.
+    fn ancestors(self) -> impl Iterator<Item = Span> {
+        std::iter::successors(Some(self), Span::call_site)
+    }
+
+    /// Joins two spans into one covering both.
+    ///
+    /// If the spans are in different sources they are joined in the innermost source both were
+    /// expanded into, using the call sites of the expansions. Returns `None` if the spans share
+    /// no source.
+    pub fn join(self, other: Span) -> Option<Span> {
+        for lhs in self.ancestors() {
+            for rhs in other.ancestors() {
+                if lhs.same_source(&rhs) {
+                    return Some(lhs.join_same_source(rhs));
+                }
+            }
+        }
+
+        None
+    }
+
+    #[inline]
+    fn join_same_source(self, rhs: Span) -> Span {
         let lhs_end = self.offset + self.length;
         let rhs_end = rhs.offset + rhs.length;
         let offset = self.offset.min(rhs.offset);
         let end = lhs_end.max(rhs_end);
 
-        let (line, column) = if self.offset > rhs.offset {
-            (rhs.line, rhs.column)
+        let (line, column, byte_offset) = if self.offset > rhs.offset {
+            (rhs.line, rhs.column, rhs.byte_offset)
         } else {
-            (self.line, self.column)
+            (self.line, self.column, self.byte_offset)
         };
 
         Self {
@@ -62,10 +199,21 @@ impl BitOr for Span {
             column,
             offset,
             length: end - offset,
+            byte_offset,
         }
     }
 }
 
+/// Joins two spans with [`Span::join`], falling back to `self` if they share no source.
+impl BitOr for Span {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.join(rhs).unwrap_or(self)
+    }
+}
+
 impl BitOrAssign for Span {
     #[inline]
     fn bitor_assign(&mut self, rhs: Self) {
@@ -73,9 +221,20 @@ impl BitOrAssign for Span {
     }
 }
 
+/// Formats the span as `path:line:column..line:column`, lines and columns are one-based.
 impl std::fmt::Debug for Span {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Span").finish()
+        let (end_line, end_column) = self.end();
+
+        write!(
+            f,
+            "{}:{}:{}..{}:{}",
+            self.path,
+            self.line + 1,
+            self.column + 1,
+            end_line + 1,
+            end_column + 1
+        )
     }
 }
 
@@ -123,7 +282,9 @@ where
 {
     type Token = T::Token;
 
-    const START: ParseStart<Self::Token> = T::START;
+    const FIRST: ParseFirst<Self::Token> = T::FIRST;
+
+    const NULLABLE: bool = true;
 
     #[inline]
     fn parse(parser: &mut impl Parser<Self::Token>) -> Result<Self, Error> {
@@ -131,14 +292,47 @@ where
 
         if let Some(t) = parser.try_parse::<T>()? {
             Ok(Self {
-                span: span | parser.span(0),
+                span: span | parser.prev_span(),
                 value: Some(t),
             })
         } else {
-            Ok(Self {
-                span: span | parser.span(0),
-                value: None,
-            })
+            Ok(Self { span, value: None })
+        }
+    }
+
+    #[inline]
+    fn symbols(&self, symbols: &mut Vec<Symbol>) {
+        if let Some(ref value) = self.value {
+            value.symbols(symbols);
+        }
+    }
+
+    #[inline]
+    fn dump(&self, dump: &mut AstDump) {
+        match self.value {
+            Some(ref value) => value.dump(dump),
+            None => dump.leaf("None"),
+        }
+    }
+
+    #[inline]
+    fn print(&self, printer: &mut Printer) {
+        if let Some(ref value) = self.value {
+            value.print(printer);
+        }
+    }
+
+    #[inline]
+    fn grammar(grammar: &mut Grammar) -> GrammarExpr {
+        GrammarExpr::optional(T::grammar(grammar))
+    }
+
+    #[inline]
+    fn generate(generator: &mut Generator) {
+        if generator.optional() {
+            T::generate(generator);
+        } else {
+            generator.leaf("None");
         }
     }
 }
@@ -158,3 +352,82 @@ impl<T> DerefMut for SpannedOption<T> {
         &mut self.value
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharsLexer, Lexer};
+
+    /// Returns spans of length one over each character of `source`.
+    fn spans(source: &'static str, path: SourcePath) -> Vec<Span> {
+        let mut lexer = CharsLexer::new_static(source, path);
+        let mut spans = Vec::new();
+
+        while !lexer.is_empty() {
+            spans.push(lexer.span(1));
+            lexer.consume();
+        }
+
+        spans
+    }
+
+    #[test]
+    fn join_through_call_sites() {
+        let main = SourcePath::Other("main");
+        let outer = spans("foo!(); bar", main);
+        let call_site = outer[0] | outer[5];
+        let inner = spans("x + y", SourcePath::expansion("foo!", call_site));
+        let bar = outer[8] | outer[10];
+
+        let joined = inner[4].join(bar).unwrap();
+
+        assert_eq!((joined.path, joined.offset, joined.length), (main, 0, 11));
+        assert_eq!(bar.join(inner[4]), Some(joined));
+        assert_eq!(inner[0].join(inner[4]).unwrap().length, 5);
+    }
+
+    #[test]
+    fn join_unrelated_sources() {
+        let a = spans("a", SourcePath::Other("a"))[0];
+        let b = spans("b", SourcePath::Other("b"))[0];
+
+        assert_eq!(a.join(b), None);
+        assert_eq!(a | b, a);
+    }
+
+    #[test]
+    fn expansions_innermost_first() {
+        let main = spans("outer!()", SourcePath::Other("main"))[0];
+        let outer = spans("inner!()", SourcePath::expansion("outer!", main))[0];
+        let inner = spans("x", SourcePath::expansion("inner!", outer))[0];
+
+        let names = inner.expansions().map(|e| e.name).collect::<Vec<_>>();
+
+        assert_eq!(names, ["inner!", "outer!"]);
+        assert_eq!(inner.call_site(), Some(outer));
+        assert_eq!(inner.origin(), main);
+        assert_eq!(main.origin(), main);
+        assert_eq!(main.expansions().count(), 0);
+    }
+
+    #[test]
+    fn expansions_are_interned() {
+        let call_site = spans("m!()", SourcePath::Generated)[0];
+        let a = SourcePath::expansion("m!", call_site);
+        let b = SourcePath::expansion(String::from("m!"), call_site);
+
+        assert!(std::ptr::eq(
+            a.as_expansion().unwrap(),
+            b.as_expansion().unwrap()
+        ));
+        assert_ne!(a, SourcePath::expansion("n!", call_site));
+    }
+
+    #[test]
+    fn debug_shows_start_and_end() {
+        let spans = spans("ab\ncd", SourcePath::Other("main"));
+
+        assert_eq!(format!("{:?}", spans[0] | spans[1]), "main:1:1..1:3");
+        assert_eq!(format!("{:?}", spans[1] | spans[4]), "main:1:2..2:3");
+    }
+}
@@ -1,7 +1,13 @@
 use dashmap::DashSet;
 
+use std::path::Path;
+
+use crate::Expansion;
+
 lazy_static::lazy_static! {
     pub static ref GLOBAL_ALLOCATOR: StringAllocator = StringAllocator::default();
+    pub static ref GLOBAL_PATH_ALLOCATOR: PathAllocator = PathAllocator::default();
+    pub static ref GLOBAL_EXPANSION_ALLOCATOR: ExpansionAllocator = ExpansionAllocator::default();
 }
 
 #[derive(Default)]
@@ -21,7 +27,51 @@ impl StringAllocator {
     }
 }
 
+#[derive(Default)]
+pub struct PathAllocator {
+    paths: DashSet<&'static Path>,
+}
+
+impl PathAllocator {
+    pub fn alloc(&self, path: &Path) -> &'static Path {
+        if !self.paths.contains(path) {
+            let path = Box::leak(path.to_path_buf().into_boxed_path());
+
+            self.paths.insert(path);
+        }
+
+        *self.paths.get(path).unwrap()
+    }
+}
+
+#[derive(Default)]
+pub struct ExpansionAllocator {
+    expansions: DashSet<&'static Expansion>,
+}
+
+impl ExpansionAllocator {
+    pub fn alloc(&self, expansion: Expansion) -> &'static Expansion {
+        if !self.expansions.contains(&expansion) {
+            let expansion = Box::leak(Box::new(expansion));
+
+            self.expansions.insert(expansion);
+        }
+
+        *self.expansions.get(&expansion).unwrap()
+    }
+}
+
 #[inline]
 pub fn static_str(string: &str) -> &'static str {
     GLOBAL_ALLOCATOR.alloc(string)
 }
+
+#[inline]
+pub fn static_path(path: &Path) -> &'static Path {
+    GLOBAL_PATH_ALLOCATOR.alloc(path)
+}
+
+#[inline]
+pub fn static_expansion(expansion: Expansion) -> &'static Expansion {
+    GLOBAL_EXPANSION_ALLOCATOR.alloc(expansion)
+}
@@ -0,0 +1,36 @@
+use crate::Span;
+
+/// A named node in a document, collected by [`Parse::symbols`](crate::Parse::symbols).
+///
+/// Nodes are marked as symbols with `#[parse(symbol = "kind")]`, the name is the text of the
+/// field marked with `#[parse(name)]`, or the first field if none is marked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// Kind given in the `symbol` attribute, e.g. `"function"`.
+    pub kind: &'static str,
+    /// Span of the entire node.
+    pub span: Span,
+    /// Span of the name of the node.
+    pub name_span: Span,
+    pub children: Vec<Symbol>,
+}
+
+impl Symbol {
+    #[inline]
+    pub fn new(kind: &'static str, span: Span, name_span: Span) -> Self {
+        Self {
+            name: name_span.text().to_string(),
+            kind,
+            span,
+            name_span,
+            children: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn with_children(mut self, children: Vec<Symbol>) -> Self {
+        self.children = children;
+        self
+    }
+}
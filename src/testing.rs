@@ -0,0 +1,513 @@
+//! Golden-file and round-trip tests for grammars, enabled with the `testing` feature.
+//!
+//! Every input file in a directory is parsed with a root [`Parse`] type, and the
+//! [`dump_ast`](crate::dump_ast) of the node, or the rendered [`Error`](crate::Error), is
+//! compared against a file next to the input with `.expected` appended to its name.
+//!
+//! ```ignore
+//! #[test]
+//! fn grammar() {
+//!     lasagna::testing::golden::<Program>("tests/golden");
+//! }
+//! ```
+//!
+//! Run the tests with `LASAGNA_BLESS=1` set to write the `.expected` files instead.
+//!
+//! [`round_trip`] checks that randomly generated sources survive being parsed, printed and
+//! parsed again, see [`RoundTrip`].
+
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    dump_ast, parse_source, print_ast, AstDump, Generated, Generator, Parse, SourcePath, Token,
+};
+
+/// Environment variable that makes [`Golden`] write expected files instead of comparing.
+pub const BLESS_VAR: &str = "LASAGNA_BLESS";
+
+const EXPECTED_EXTENSION: &str = "expected";
+
+/// Runs the golden tests in `dir`, see [`Golden::run`].
+#[track_caller]
+pub fn golden<P>(dir: impl AsRef<Path>)
+where
+    P: Parse,
+    P::Token: Token<char>,
+{
+    Golden::new(dir).run::<P>();
+}
+
+/// Golden-file test runner for a directory of inputs.
+#[derive(Clone, Debug)]
+pub struct Golden {
+    dir: PathBuf,
+    extension: Option<String>,
+    bless: bool,
+}
+
+impl Golden {
+    /// Creates a runner for `dir`, blessing if [`BLESS_VAR`] is set.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        let bless = env::var_os(BLESS_VAR).is_some_and(|var| var != "0" && !var.is_empty());
+
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            extension: None,
+            bless,
+        }
+    }
+
+    /// Only treats files with `extension` as inputs.
+    ///
+    /// Without an extension every file with an extension is an input, except for expected
+    /// files, hidden files, editor backups and `.md` files.
+    #[inline]
+    pub fn extension(mut self, extension: impl Into<String>) -> Self {
+        self.extension = Some(extension.into());
+        self
+    }
+
+    /// Writes the expected files instead of comparing against them.
+    #[inline]
+    pub fn bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        self
+    }
+
+    /// Runs every test, panicking with a report of all mismatches.
+    #[track_caller]
+    pub fn run<P>(&self)
+    where
+        P: Parse,
+        P::Token: Token<char>,
+    {
+        match self.check::<P>() {
+            Ok(failures) if failures.is_empty() => {}
+            Ok(failures) => panic!("{}", Failures(&failures)),
+            Err(err) => panic!(
+                "failed to run golden tests in '{}': {}",
+                self.dir.display(),
+                err
+            ),
+        }
+    }
+
+    /// Runs every test and returns the mismatches.
+    pub fn check<P>(&self) -> io::Result<Vec<Failure>>
+    where
+        P: Parse,
+        P::Token: Token<char>,
+    {
+        let mut failures = Vec::new();
+
+        for input in self.inputs()? {
+            let source = fs::read_to_string(&input)?;
+            let name = input.strip_prefix(&self.dir).unwrap_or(&input);
+            let actual = render::<P>(&source, SourcePath::path(name));
+
+            let expected_path = expected_path(&input);
+
+            if self.bless {
+                fs::write(&expected_path, &actual)?;
+                continue;
+            }
+
+            let expected = match fs::read_to_string(&expected_path) {
+                Ok(expected) => Some(expected),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                Err(err) => return Err(err),
+            };
+
+            if expected.as_deref() != Some(actual.as_str()) {
+                failures.push(Failure {
+                    input,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        Ok(failures)
+    }
+
+    /// Returns the input files in the directory and its subdirectories, sorted by path.
+    fn inputs(&self) -> io::Result<Vec<PathBuf>> {
+        let mut inputs = Vec::new();
+        let mut dirs = vec![self.dir.clone()];
+
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+
+                if path.is_dir() {
+                    if !is_ignored(&path) {
+                        dirs.push(path);
+                    }
+                } else if self.is_input(&path) {
+                    inputs.push(path);
+                }
+            }
+        }
+
+        inputs.sort();
+
+        Ok(inputs)
+    }
+
+    fn is_input(&self, path: &Path) -> bool {
+        let extension = path.extension().and_then(|ext| ext.to_str());
+
+        match self.extension {
+            Some(ref expected) => extension == Some(expected.as_str()),
+            None => match extension {
+                Some(extension) => !IGNORED_EXTENSIONS.contains(&extension) && !is_ignored(path),
+                None => false,
+            },
+        }
+    }
+}
+
+/// Extensions of files that are never inputs when no extension is set.
+const IGNORED_EXTENSIONS: &[&str] = &[EXPECTED_EXTENSION, "bak", "orig", "swp", "md"];
+
+/// Returns `true` for hidden files and editor backups.
+fn is_ignored(path: &Path) -> bool {
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name.starts_with('.') || name.ends_with('~'),
+        None => true,
+    }
+}
+
+/// Parses `source` and renders either the dump of the node or the error.
+pub fn render<P>(source: &str, path: SourcePath) -> String
+where
+    P: Parse,
+    P::Token: Token<char>,
+{
+    match parse_source::<P>(source, path) {
+        Ok(node) => dump_ast(&node),
+        Err(err) => err.to_string(),
+    }
+}
+
+fn expected_path(input: &Path) -> PathBuf {
+    let mut path = input.as_os_str().to_owned();
+    path.push(".");
+    path.push(EXPECTED_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// A golden test whose output didn't match the expected file.
+#[derive(Clone, Debug)]
+pub struct Failure {
+    pub input: PathBuf,
+    /// Contents of the expected file, `None` if it doesn't exist.
+    pub expected: Option<String>,
+    pub actual: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expected = match self.expected {
+            Some(ref expected) => expected,
+            None => {
+                writeln!(f, "{}: missing expected file", self.input.display())?;
+                return writeln!(f, "actual:\n{}", self.actual);
+            }
+        };
+
+        writeln!(f, "{}: output differs from expected", self.input.display())?;
+
+        let mut expected_lines = expected.lines();
+        let mut actual_lines = self.actual.lines();
+
+        loop {
+            match (expected_lines.next(), actual_lines.next()) {
+                (None, None) => break,
+                (Some(expected), Some(actual)) if expected == actual => {
+                    writeln!(f, "  {}", actual)?;
+                }
+                (expected, actual) => {
+                    if let Some(expected) = expected {
+                        writeln!(f, "- {}", expected)?;
+                    }
+
+                    if let Some(actual) = actual {
+                        writeln!(f, "+ {}", actual)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct Failures<'a>(&'a [Failure]);
+
+impl fmt::Display for Failures<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} golden test(s) failed", self.0.len())?;
+
+        for failure in self.0 {
+            writeln!(f)?;
+            write!(f, "{}", failure)?;
+        }
+
+        writeln!(f)?;
+        write!(f, "rerun with {}=1 to update the expected files", BLESS_VAR)
+    }
+}
+
+/// Environment variable that sets the seed of [`RoundTrip`].
+pub const SEED_VAR: &str = "LASAGNA_SEED";
+
+/// Runs the round-trip tests for `P`, see [`RoundTrip::run`].
+#[track_caller]
+pub fn round_trip<P>(cases: usize)
+where
+    P: Parse,
+    P::Token: Token<char>,
+{
+    RoundTrip::new(cases).run::<P>();
+}
+
+/// Property-based round-trip tester.
+///
+/// Each case generates a random source with [`Parse::generate`] and checks that it parses to
+/// the tree the generator chose. A source that parses to a different tree points at an
+/// ambiguity in the grammar. The parsed node is then printed with [`Parse::print`] and
+/// reparsed, which must again give the same tree.
+#[derive(Clone, Debug)]
+pub struct RoundTrip {
+    cases: usize,
+    seed: u64,
+    max_depth: usize,
+}
+
+impl RoundTrip {
+    /// Creates a tester running `cases` cases, seeded by [`SEED_VAR`] if set.
+    pub fn new(cases: usize) -> Self {
+        let seed = env::var(SEED_VAR)
+            .ok()
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or(0);
+
+        Self {
+            cases,
+            seed,
+            max_depth: Generator::DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    #[inline]
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    #[inline]
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Runs every case, panicking on the first failure.
+    #[track_caller]
+    pub fn run<P>(&self)
+    where
+        P: Parse,
+        P::Token: Token<char>,
+    {
+        if let Err(failure) = self.check::<P>() {
+            panic!("{}", failure);
+        }
+    }
+
+    /// Runs every case and returns the number of cases that could be generated.
+    pub fn check<P>(&self) -> Result<usize, RoundTripFailure>
+    where
+        P: Parse,
+        P::Token: Token<char>,
+    {
+        let mut generated = 0;
+        let mut reason = None;
+
+        for case in 0..self.cases as u64 {
+            let seed = self.seed.wrapping_add(case);
+
+            let mut generator = Generator::new(seed).with_max_depth(self.max_depth);
+            P::generate(&mut generator);
+
+            let source = match generator.finish() {
+                Ok(source) => source,
+                Err(err) => {
+                    reason = Some(err);
+                    continue;
+                }
+            };
+
+            generated += 1;
+            Self::case::<P>(seed, source)?;
+        }
+
+        match reason {
+            Some(reason) if generated == 0 => Err(RoundTripFailure {
+                seed: self.seed,
+                source: String::new(),
+                printed: None,
+                kind: RoundTripFailureKind::Generate(reason),
+            }),
+            _ => Ok(generated),
+        }
+    }
+
+    fn case<P>(seed: u64, generated: Generated) -> Result<(), RoundTripFailure>
+    where
+        P: Parse,
+        P::Token: Token<char>,
+    {
+        let Generated {
+            source,
+            structure: generated,
+        } = generated;
+        let failure = |printed, kind| RoundTripFailure {
+            seed,
+            source: source.clone(),
+            printed,
+            kind,
+        };
+
+        let node = parse_source::<P>(&source, SourcePath::Generated)
+            .map_err(|err| failure(None, RoundTripFailureKind::Parse(err.to_string())))?;
+
+        let parsed = structure(&node);
+
+        if parsed != generated {
+            return Err(failure(
+                None,
+                RoundTripFailureKind::Ambiguous { generated, parsed },
+            ));
+        }
+
+        let printed = print_ast(&node);
+
+        let reparsed = parse_source::<P>(&printed, SourcePath::Generated).map_err(|err| {
+            failure(
+                Some(printed.clone()),
+                RoundTripFailureKind::Reparse(err.to_string()),
+            )
+        })?;
+
+        let reparsed = structure(&reparsed);
+
+        if reparsed != parsed {
+            return Err(failure(
+                Some(printed),
+                RoundTripFailureKind::Mismatch {
+                    expected: parsed,
+                    actual: reparsed,
+                },
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the dump of `node` without spans.
+fn structure(node: &impl Parse) -> String {
+    let mut dump = AstDump::without_spans();
+    node.dump(&mut dump);
+    dump.finish()
+}
+
+/// A round-trip case that failed, see [`RoundTrip`].
+#[derive(Clone, Debug)]
+pub struct RoundTripFailure {
+    /// Seed that reproduces the failure.
+    pub seed: u64,
+    /// Generated source.
+    pub source: String,
+    /// Source printed from the parsed node.
+    pub printed: Option<String>,
+    pub kind: RoundTripFailureKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum RoundTripFailureKind {
+    /// No case could be generated.
+    Generate(String),
+    /// The generated source failed to parse.
+    Parse(String),
+    /// The generated source parsed to a different tree than was generated.
+    Ambiguous { generated: String, parsed: String },
+    /// The printed source failed to parse.
+    Reparse(String),
+    /// The reparsed node differs from the parsed node.
+    Mismatch { expected: String, actual: String },
+}
+
+impl fmt::Display for RoundTripFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let RoundTripFailureKind::Generate(ref reason) = self.kind {
+            return write!(f, "could not generate any source: {}", reason);
+        }
+
+        writeln!(f, "round trip failed with {}={}", SEED_VAR, self.seed)?;
+        writeln!(f, "source: {}", self.source)?;
+
+        if let Some(ref printed) = self.printed {
+            writeln!(f, "printed: {}", printed)?;
+        }
+
+        match self.kind {
+            RoundTripFailureKind::Generate(_) => Ok(()),
+            RoundTripFailureKind::Parse(ref err) => {
+                write!(f, "generated source failed to parse: {}", err)
+            }
+            RoundTripFailureKind::Ambiguous {
+                ref generated,
+                ref parsed,
+            } => write!(
+                f,
+                "generated source parsed differently\ngenerated:\n{}parsed:\n{}",
+                generated, parsed
+            ),
+            RoundTripFailureKind::Reparse(ref err) => {
+                write!(f, "printed source failed to parse: {}", err)
+            }
+            RoundTripFailureKind::Mismatch {
+                ref expected,
+                ref actual,
+            } => write!(f, "parsed:\n{}reparsed:\n{}", expected, actual),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inputs_skip_non_sources() {
+        let golden = Golden::new("tests");
+
+        assert!(golden.is_input(Path::new("tests/a.json")));
+        assert!(!golden.is_input(Path::new("tests/a.json.expected")));
+        assert!(!golden.is_input(Path::new("tests/a.json~")));
+        assert!(!golden.is_input(Path::new("tests/a.json.bak")));
+        assert!(!golden.is_input(Path::new("tests/.a.json.swp")));
+        assert!(!golden.is_input(Path::new("tests/.gitkeep")));
+        assert!(!golden.is_input(Path::new("tests/README")));
+        assert!(!golden.is_input(Path::new("tests/README.md")));
+
+        let golden = golden.extension("cfg");
+
+        assert!(golden.is_input(Path::new("tests/a.cfg")));
+        assert!(!golden.is_input(Path::new("tests/a.json")));
+    }
+}
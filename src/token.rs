@@ -1,6 +1,14 @@
-use std::{iter::Peekable, str::Chars};
+use std::{
+    fmt::Display,
+    iter::Peekable,
+    ops::{Deref, DerefMut},
+    str::Chars,
+};
 
-use crate::{string_allocator::static_str, Error, SourcePath, Span};
+use crate::{
+    string_allocator::static_str, AstDump, Error, Grammar, GrammarExpr, Parse, ParseFirst, Parser,
+    Printer, SourcePath, SourceText, Span, Spanned, TokenSet,
+};
 
 pub trait Token<Source = char>: Lex<Source> {
     type Kind: TokenKind;
@@ -9,16 +17,143 @@ pub trait Token<Source = char>: Lex<Source> {
 }
 
 pub trait TokenKind: Copy + PartialEq + Eq + 'static {
+    /// Every kind, ordered by [`TokenKind::index`].
+    const ALL: &'static [Self];
+
+    /// The number of kinds.
+    const COUNT: usize = Self::ALL.len();
+
+    /// Every class returned by [`TokenKind::class`].
+    const CLASSES: &'static [&'static str] = &[];
+
     fn name(&self) -> &str;
+
+    /// Returns the dense index of the kind, used by [`TokenSet`](crate::TokenSet).
+    ///
+    /// Indices must be less than [`TokenKind::COUNT`] and
+    /// [`TokenSet::CAPACITY`](crate::TokenSet::CAPACITY), and `Self::ALL[kind.index()] == kind`.
+    fn index(&self) -> usize;
+
+    /// Returns the kind with `index`, the inverse of [`TokenKind::index`].
+    #[inline]
+    fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+
+    /// Returns a human readable description of the kind, the literal text for literal tokens.
+    #[inline]
+    fn description(&self) -> &str {
+        self.name()
+    }
+
+    /// Returns whether the kind matches a literal string, set with `#[token = "..."]`.
+    #[inline]
+    fn is_literal(&self) -> bool {
+        false
+    }
+
+    /// Returns the description used in error messages, quoted for literal tokens.
+    #[inline]
+    fn label(&self) -> String {
+        if self.is_literal() {
+            format!("`{}`", self.description())
+        } else {
+            self.description().to_string()
+        }
+    }
+
+    /// Returns the highlighting class of the token, set with `#[token(class = "...")]`.
+    #[inline]
+    fn class(&self) -> Option<&'static str> {
+        None
+    }
 }
 
 pub trait Lex<Source = char>: Sized {
     fn lex(lexer: &mut impl Lexer<Output = Source>) -> Result<Self, Error>;
 }
 
+/// A token together with the [`Span`] it was lexed from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SpannedToken<T> {
+    pub span: Span,
+    pub token: T,
+}
+
+impl<T> SpannedToken<T> {
+    #[inline]
+    pub fn new(span: Span, token: T) -> Self {
+        Self { span, token }
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.token
+    }
+}
+
+impl<T> Spanned for SpannedToken<T> {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl<T: Token> Parse for SpannedToken<T> {
+    type Token = T;
+
+    const FIRST: ParseFirst<Self::Token> = TokenSet::FULL;
+
+    #[inline]
+    fn parse(parser: &mut impl Parser<Self::Token>) -> Result<Self, Error> {
+        parser.next()
+    }
+
+    #[inline]
+    fn dump(&self, dump: &mut AstDump) {
+        dump.token(self.kind().name(), self.span);
+    }
+
+    #[inline]
+    fn print(&self, printer: &mut Printer) {
+        printer.token(&self.span.text());
+    }
+
+    #[inline]
+    fn grammar(_: &mut Grammar) -> GrammarExpr {
+        GrammarExpr::Token(String::from("token"))
+    }
+}
+
+impl<T> Deref for SpannedToken<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.token
+    }
+}
+
+impl<T> DerefMut for SpannedToken<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.token
+    }
+}
+
+impl<T: Display> Display for SpannedToken<T> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.token.fmt(f)
+    }
+}
+
 pub trait Lexer {
     type Output;
 
+    /// Saved cursor position, see [`Lexer::checkpoint`].
+    type Checkpoint: Clone;
+
     /// Returns a [`Span`] at the cursor of the lexer.
     fn span(&mut self, length: usize) -> Span;
 
@@ -38,7 +173,27 @@ pub trait Lexer {
         self.next();
     }
 
-    fn fork(&mut self) -> Self;
+    /// Saves the current cursor position, which can later be restored with [`Lexer::rewind`].
+    fn checkpoint(&self) -> Self::Checkpoint;
+
+    /// Restores the cursor to a position saved by [`Lexer::checkpoint`].
+    fn rewind(&mut self, checkpoint: Self::Checkpoint);
+
+    /// Runs `f`, rewinding the lexer if it returns an error.
+    #[inline]
+    fn attempt<R>(&mut self, f: impl FnOnce(&mut Self) -> Result<R, Error>) -> Result<R, Error>
+    where
+        Self: Sized,
+    {
+        let checkpoint = self.checkpoint();
+        let result = f(self);
+
+        if result.is_err() {
+            self.rewind(checkpoint);
+        }
+
+        result
+    }
 }
 
 #[derive(Clone)]
@@ -46,8 +201,11 @@ pub struct CharsLexer<'a> {
     line: usize,
     column: usize,
     offset: usize,
+    byte_offset: usize,
     path: SourcePath,
-    source: &'a str,
+    /// Text referenced by [`Span`]s, an interned copy of the source unless it's `'static` or
+    /// shared.
+    source: SourceText,
     chars: Peekable<Chars<'a>>,
 }
 
@@ -58,25 +216,67 @@ impl<'a> CharsLexer<'a> {
             line: 0,
             column: 0,
             offset: 0,
+            byte_offset: 0,
+            path,
+            source: SourceText::from_static(static_str(source)),
+            chars: source.chars().peekable(),
+        }
+    }
+
+    /// Creates a lexer over `source` whose spans refer to it as `text`.
+    #[inline]
+    pub(crate) fn with_source_text(source: &'a str, text: SourceText, path: SourcePath) -> Self {
+        Self {
+            line: 0,
+            column: 0,
+            offset: 0,
+            byte_offset: 0,
             path,
-            source,
+            source: text,
             chars: source.chars().peekable(),
         }
     }
 }
 
+impl CharsLexer<'static> {
+    /// Creates a lexer over a source that is already `'static`, without interning it.
+    #[inline]
+    pub fn new_static(source: &'static str, path: SourcePath) -> Self {
+        Self {
+            line: 0,
+            column: 0,
+            offset: 0,
+            byte_offset: 0,
+            path,
+            source: SourceText::from_static(source),
+            chars: source.chars().peekable(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CharsCheckpoint<'a> {
+    line: usize,
+    column: usize,
+    offset: usize,
+    byte_offset: usize,
+    chars: Peekable<Chars<'a>>,
+}
+
 impl<'a> Lexer for CharsLexer<'a> {
     type Output = char;
+    type Checkpoint = CharsCheckpoint<'a>;
 
     #[inline]
     fn span(&mut self, length: usize) -> Span {
         Span {
             path: self.path,
-            source: static_str(self.source),
+            source: self.source,
             line: self.line,
             column: self.column,
             offset: self.offset,
             length,
+            byte_offset: self.byte_offset,
         }
     }
 
@@ -84,6 +284,7 @@ impl<'a> Lexer for CharsLexer<'a> {
     fn next(&mut self) -> Option<char> {
         if let Some(c) = self.chars.next() {
             self.offset += 1;
+            self.byte_offset += c.len_utf8();
 
             if c == '\n' {
                 self.column = 0;
@@ -109,11 +310,13 @@ impl<'a> Lexer for CharsLexer<'a> {
 
     #[inline]
     fn expect(&mut self, expected: Self::Output) -> Result<(), Error> {
+        let span = self.span(1);
+
         if let Some(next_char) = self.next() {
             if next_char == expected {
                 Ok(())
             } else {
-                Err(Error::expected(self.span(1), expected, next_char))
+                Err(Error::expected(span, expected, next_char))
             }
         } else {
             Err(Error::expected(self.span(0), expected, "eof"))
@@ -121,7 +324,22 @@ impl<'a> Lexer for CharsLexer<'a> {
     }
 
     #[inline]
-    fn fork(&mut self) -> Self {
-        self.clone()
+    fn checkpoint(&self) -> Self::Checkpoint {
+        CharsCheckpoint {
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+            byte_offset: self.byte_offset,
+            chars: self.chars.clone(),
+        }
+    }
+
+    #[inline]
+    fn rewind(&mut self, checkpoint: Self::Checkpoint) {
+        self.line = checkpoint.line;
+        self.column = checkpoint.column;
+        self.offset = checkpoint.offset;
+        self.byte_offset = checkpoint.byte_offset;
+        self.chars = checkpoint.chars;
     }
 }
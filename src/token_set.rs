@@ -0,0 +1,228 @@
+use std::{
+    fmt::{self, Debug},
+    hash::{Hash, Hasher},
+    iter::FromIterator,
+    marker::PhantomData,
+    ops::{BitAnd, BitOr, Sub},
+};
+
+use crate::{Parser, Token, TokenKind};
+
+const WORDS: usize = 4;
+
+/// Set of token kinds, a bitset over [`TokenKind::index`].
+///
+/// Most operations are `const`, so the [`Parse::FIRST`](crate::Parse::FIRST) of derived types is
+/// computed at compile time.
+pub struct TokenSet<K> {
+    bits: [u64; WORDS],
+    kind: PhantomData<fn() -> K>,
+}
+
+impl<K> TokenSet<K> {
+    /// Maximum number of token kinds a set can hold.
+    pub const CAPACITY: usize = WORDS * 64;
+
+    pub const EMPTY: Self = Self {
+        bits: [0; WORDS],
+        kind: PhantomData,
+    };
+
+    /// Set containing every index, used for [`StartTokens::All`](crate::StartTokens::All).
+    pub const FULL: Self = Self {
+        bits: [u64::MAX; WORDS],
+        kind: PhantomData,
+    };
+
+    /// Returns `self` with the kind at `index` added.
+    #[inline]
+    pub const fn with(mut self, index: usize) -> Self {
+        self.bits[index / 64] |= 1 << (index % 64);
+        self
+    }
+
+    #[inline]
+    pub const fn contains_index(&self, index: usize) -> bool {
+        index < Self::CAPACITY && self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    #[inline]
+    pub const fn union(mut self, other: Self) -> Self {
+        let mut i = 0;
+
+        while i < WORDS {
+            self.bits[i] |= other.bits[i];
+            i += 1;
+        }
+
+        self
+    }
+
+    #[inline]
+    pub const fn intersection(mut self, other: Self) -> Self {
+        let mut i = 0;
+
+        while i < WORDS {
+            self.bits[i] &= other.bits[i];
+            i += 1;
+        }
+
+        self
+    }
+
+    #[inline]
+    pub const fn difference(mut self, other: Self) -> Self {
+        let mut i = 0;
+
+        while i < WORDS {
+            self.bits[i] &= !other.bits[i];
+            i += 1;
+        }
+
+        self
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        let mut i = 0;
+
+        while i < WORDS {
+            if self.bits[i] != 0 {
+                return false;
+            }
+
+            i += 1;
+        }
+
+        true
+    }
+
+    /// Reinterprets the indices of the set as indices of `U`.
+    #[inline]
+    pub(crate) const fn cast<U>(self) -> TokenSet<U> {
+        TokenSet {
+            bits: self.bits,
+            kind: PhantomData,
+        }
+    }
+
+    /// Iterates over the indices in the set, in ascending order.
+    #[inline]
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..Self::CAPACITY).filter(move |&index| self.contains_index(index))
+    }
+}
+
+impl<K: TokenKind> TokenSet<K> {
+    #[inline]
+    pub fn insert(&mut self, kind: K) {
+        *self = self.with(kind.index());
+    }
+
+    #[inline]
+    pub fn contains(&self, kind: &K) -> bool {
+        self.contains_index(kind.index())
+    }
+
+    /// Iterates over the kinds in the set, in index order.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = K> + '_ {
+        self.indices().filter_map(K::from_index)
+    }
+
+    /// Returns the number of kinds in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns whether the next token of `parser` is in `self`, `None` if unknown.
+    #[inline]
+    pub fn is_next<T>(&self, parser: &mut impl Parser<T>) -> Option<bool>
+    where
+        T: Token<Kind = K>,
+    {
+        match parser.peek() {
+            Ok(Some(token)) => Some(self.contains(&token.kind())),
+            Ok(None) => Some(false),
+            Err(_) => None,
+        }
+    }
+}
+
+impl<K> Clone for TokenSet<K> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for TokenSet<K> {}
+
+impl<K> Default for TokenSet<K> {
+    #[inline]
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl<K> PartialEq for TokenSet<K> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.bits == other.bits
+    }
+}
+
+impl<K> Eq for TokenSet<K> {}
+
+impl<K> Hash for TokenSet<K> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits.hash(state);
+    }
+}
+
+impl<K: TokenKind + Debug> Debug for TokenSet<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K: TokenKind> FromIterator<K> for TokenSet<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut set = Self::EMPTY;
+
+        for kind in iter {
+            set.insert(kind);
+        }
+
+        set
+    }
+}
+
+impl<K> BitOr for TokenSet<K> {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.union(rhs)
+    }
+}
+
+impl<K> BitAnd for TokenSet<K> {
+    type Output = Self;
+
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.intersection(rhs)
+    }
+}
+
+impl<K> Sub for TokenSet<K> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.difference(rhs)
+    }
+}
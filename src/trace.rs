@@ -0,0 +1,270 @@
+//! Tracing of derived parsers, enabled with the `trace` feature.
+//!
+//! Every derived [`Parse::parse`](crate::Parse::parse) reports entering and leaving the node,
+//! the source text it starts at, the enum variant it chose, and [`Parser::try_parse`] and
+//! [`Parser::attempt`] report their decisions and backtracks. Events are sent to the sink set
+//! with [`set_sink`], nothing is reported until one is set.
+//!
+//! ```ignore
+//! lasagna::trace::set_sink(lasagna::trace::StderrSink);
+//! ```
+//!
+//! With the `tracing` feature, [`TracingSink`] forwards the events to the `tracing`
+//! subscriber instead. Without the `trace` feature all of this is compiled out.
+
+use std::{cell::Cell, fmt, sync::RwLock};
+
+use crate::{Error, Parser, Span};
+
+lazy_static::lazy_static! {
+    static ref SINK: RwLock<Option<Box<dyn Sink>>> = RwLock::new(None);
+}
+
+thread_local! {
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Receiver of [`Event`]s.
+pub trait Sink: Send + Sync {
+    fn event(&self, event: &Event<'_>);
+}
+
+/// Sets the sink that receives all events.
+pub fn set_sink(sink: impl Sink + 'static) {
+    *SINK.write().unwrap() = Some(Box::new(sink));
+}
+
+/// Removes the sink, disabling tracing.
+pub fn clear_sink() {
+    *SINK.write().unwrap() = None;
+}
+
+/// Returns whether a sink is set.
+#[inline]
+pub fn is_enabled() -> bool {
+    SINK.read().map(|sink| sink.is_some()).unwrap_or(false)
+}
+
+fn emit(kind: EventKind<'_>) {
+    if let Some(ref sink) = *SINK.read().unwrap() {
+        let depth = DEPTH.with(Cell::get);
+
+        sink.event(&Event { depth, kind });
+    }
+}
+
+/// An event while parsing, `depth` is the number of derived nodes being parsed.
+#[derive(Clone, Copy, Debug)]
+pub struct Event<'a> {
+    pub depth: usize,
+    pub kind: EventKind<'a>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum EventKind<'a> {
+    /// Started parsing `node` at `span`, `text` is the start of the source at `span` up to
+    /// the end of the line, `None` at the end of the input.
+    ///
+    /// The text is read from the source rather than lexed, so tracing doesn't change what
+    /// the parser does.
+    Enter {
+        node: &'static str,
+        span: Span,
+        text: Option<&'a str>,
+    },
+    /// Chose `variant` of the enum `node`.
+    Variant {
+        node: &'static str,
+        variant: &'static str,
+    },
+    /// [`Parser::try_parse`] checked whether `node` is next, `None` if unknown.
+    TryParse {
+        node: &'static str,
+        span: Span,
+        next: Option<bool>,
+    },
+    /// [`Parser::attempt`] rewound to `span` after `error`.
+    Backtrack { span: Span, error: &'a Error },
+    /// Finished parsing `node`, covering `span` if it succeeded.
+    Exit {
+        node: &'static str,
+        result: Result<Span, &'a Error>,
+    },
+}
+
+impl fmt::Display for Event<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            EventKind::Enter { node, span, text } => {
+                write!(f, "> {} at {:?}", node, span)?;
+
+                match text {
+                    Some(text) => write!(f, ", next {:?}", text),
+                    None => write!(f, ", at eof"),
+                }
+            }
+            EventKind::Variant { node, variant } => write!(f, "= {}::{}", node, variant),
+            EventKind::TryParse { node, span, next } => {
+                let next = match next {
+                    Some(true) => "yes",
+                    Some(false) => "no",
+                    None => "unknown, attempting",
+                };
+
+                write!(f, "? {} at {:?}: {}", node, span, next)
+            }
+            EventKind::Backtrack { span, error } => {
+                write!(f, "<- backtrack to {:?}: {}", span, error.msg())
+            }
+            EventKind::Exit { node, result } => match result {
+                Ok(span) => write!(f, "< {} ok {:?}", node, span),
+                Err(error) => write!(f, "< {} failed: {}", node, error.msg()),
+            },
+        }
+    }
+}
+
+/// Sink that writes events to stderr, indented by depth.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StderrSink;
+
+impl Sink for StderrSink {
+    fn event(&self, event: &Event<'_>) {
+        eprintln!("{:indent$}{}", "", event, indent = event.depth * 2);
+    }
+}
+
+/// Sink that emits events to the `tracing` subscriber at the trace level.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TracingSink;
+
+#[cfg(feature = "tracing")]
+impl Sink for TracingSink {
+    fn event(&self, event: &Event<'_>) {
+        tracing::trace!(target: "lasagna", depth = event.depth, "{}", event);
+    }
+}
+
+/// A derived node being parsed, see [`__trace_parse`](crate::__trace_parse).
+///
+/// Nodes entered while no sink is set do no work. The depth is restored when the node is
+/// dropped, so it stays balanced if parsing unwinds.
+#[doc(hidden)]
+pub struct Node {
+    node: &'static str,
+    /// Start of the node, `None` if tracing was off or the node has exited.
+    start: Option<Span>,
+}
+
+impl Node {
+    pub fn enter<T>(node: &'static str, parser: &mut impl Parser<T>) -> Self {
+        if !is_enabled() {
+            return Self { node, start: None };
+        }
+
+        let start = parser.span(0);
+        let text = upcoming_text(start);
+
+        emit(EventKind::Enter {
+            node,
+            span: start,
+            text: text.as_deref(),
+        });
+
+        DEPTH.with(|depth| depth.set(depth.get() + 1));
+
+        Self {
+            node,
+            start: Some(start),
+        }
+    }
+
+    pub fn exit<T, R>(mut self, parser: &impl Parser<T>, result: &Result<R, Error>) {
+        let start = match self.start.take() {
+            Some(start) => start,
+            None => return,
+        };
+
+        leave();
+
+        let result = match result {
+            Ok(_) => Ok(start | parser.prev_span()),
+            Err(error) => Err(error),
+        };
+
+        emit(EventKind::Exit {
+            node: self.node,
+            result,
+        });
+    }
+}
+
+impl Drop for Node {
+    #[inline]
+    fn drop(&mut self) {
+        if self.start.is_some() {
+            leave();
+        }
+    }
+}
+
+#[inline]
+fn leave() {
+    DEPTH.with(|depth| depth.set(depth.get().saturating_sub(1)));
+}
+
+/// Number of characters of source shown when entering a node.
+const UPCOMING_CHARS: usize = 16;
+
+/// Returns the source following `span` up to the end of the line, `None` at the end of the
+/// input.
+fn upcoming_text(span: Span) -> Option<String> {
+    let source = span.source.get();
+    let rest = source.get(span.byte_range().start..)?;
+
+    if rest.is_empty() {
+        return None;
+    }
+
+    let line = rest.split('\n').next().unwrap_or_default();
+
+    Some(line.chars().take(UPCOMING_CHARS).collect())
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn variant(node: &'static str, variant: &'static str) {
+    emit(EventKind::Variant { node, variant });
+}
+
+#[inline]
+pub(crate) fn try_parse(node: &'static str, span: Span, next: Option<bool>) {
+    emit(EventKind::TryParse { node, span, next });
+}
+
+#[inline]
+pub(crate) fn backtrack(span: Span, error: &Error) {
+    emit(EventKind::Backtrack { span, error });
+}
+
+/// Traces a derived parse, `$body` is the parse itself.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace_parse {
+    ($ty:ty, $parser:ident, $body:expr) => {{
+        let node = $crate::trace::Node::enter(::std::any::type_name::<$ty>(), $parser);
+        let result = $body;
+        node.exit($parser, &result);
+        result
+    }};
+}
+
+/// Traces the variant chosen by a derived enum.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace_variant {
+    ($ty:ty, $variant:expr) => {
+        $crate::trace::variant(::std::any::type_name::<$ty>(), $variant)
+    };
+}
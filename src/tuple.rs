@@ -0,0 +1,91 @@
+use crate::{
+    first_of, nullable_of, AstDump, Error, Generator, Grammar, GrammarExpr, Parse, ParseFirst,
+    Parser, Printer, Symbol, TokenSet,
+};
+
+macro_rules! impl_tuple {
+    ($first:ident $(, $ty:ident)*) => {
+        /// Parses each element in order.
+        impl<$first: Parse $(, $ty: Parse<Token = $first::Token>)*> Parse for ($first, $($ty,)*) {
+            type Token = $first::Token;
+
+            #[allow(unused_assignments)]
+            const FIRST: ParseFirst<Self::Token> = {
+                let mut first = TokenSet::EMPTY;
+                let mut nullable = true;
+
+                if nullable {
+                    first = first.union(first_of::<$first>());
+                    nullable = nullable_of::<$first>();
+                }
+
+                $(
+                    if nullable {
+                        first = first.union(first_of::<$ty>());
+                        nullable = nullable_of::<$ty>();
+                    }
+                )*
+
+                first
+            };
+
+            const NULLABLE: bool = nullable_of::<$first>() $(&& nullable_of::<$ty>())*;
+
+            #[inline]
+            fn parse(parser: &mut impl Parser<Self::Token>) -> Result<Self, Error> {
+                Ok(($first::parse(parser)?, $($ty::parse(parser)?,)*))
+            }
+
+            #[inline]
+            #[allow(non_snake_case)]
+            fn symbols(&self, symbols: &mut Vec<Symbol>) {
+                let ($first, $($ty,)*) = self;
+
+                $first.symbols(symbols);
+                $($ty.symbols(symbols);)*
+            }
+
+            #[inline]
+            #[allow(non_snake_case)]
+            fn dump(&self, dump: &mut AstDump) {
+                let ($first, $($ty,)*) = self;
+
+                dump.node("Tuple", |dump| {
+                    $first.dump(dump);
+                    $($ty.dump(dump);)*
+                });
+            }
+
+            #[inline]
+            #[allow(non_snake_case)]
+            fn print(&self, printer: &mut Printer) {
+                let ($first, $($ty,)*) = self;
+
+                $first.print(printer);
+                $($ty.print(printer);)*
+            }
+
+            #[inline]
+            fn generate(generator: &mut Generator) {
+                generator.node("Tuple", |generator| {
+                    $first::generate(generator);
+                    $($ty::generate(generator);)*
+                });
+            }
+
+            #[inline]
+            fn grammar(grammar: &mut Grammar) -> GrammarExpr {
+                GrammarExpr::Sequence(vec![$first::grammar(grammar), $($ty::grammar(grammar),)*])
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+impl_tuple!(A, B, C, D, E);
+impl_tuple!(A, B, C, D, E, F);
+impl_tuple!(A, B, C, D, E, F, G);
+impl_tuple!(A, B, C, D, E, F, G, H);
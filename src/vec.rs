@@ -1,3 +1,81 @@
-use std::ops::{Deref, DerefMut};
+use crate::{
+    no_progress, AstDump, Error, Generator, Grammar, GrammarExpr, Parse, ParseFirst, Parser,
+    Printer, Symbol,
+};
 
-use crate::{Error, Parse, Parser, Span, Spanned, Token};
+/// Parses `T` as long as it is next.
+///
+/// Returns an error if `T` is parsed without consuming any input, since the repetition would
+/// otherwise never end.
+impl<T: Parse> Parse for Vec<T> {
+    type Token = T::Token;
+
+    const FIRST: ParseFirst<Self::Token> = T::FIRST;
+
+    const NULLABLE: bool = true;
+
+    fn parse(parser: &mut impl Parser<Self::Token>) -> Result<Self, Error> {
+        let mut items = Vec::new();
+
+        loop {
+            let start = parser.span(0);
+
+            match parser.try_parse::<T>()? {
+                Some(item) => items.push(item),
+                None => break,
+            }
+
+            if parser.span(0) == start {
+                return Err(no_progress(
+                    parser.panics_on_no_progress(),
+                    start,
+                    format!(
+                        "'{}' was parsed without consuming input in a repetition",
+                        std::any::type_name::<T>()
+                    ),
+                ));
+            }
+        }
+
+        Ok(items)
+    }
+
+    #[inline]
+    fn symbols(&self, symbols: &mut Vec<Symbol>) {
+        for item in self {
+            item.symbols(symbols);
+        }
+    }
+
+    #[inline]
+    fn dump(&self, dump: &mut AstDump) {
+        dump.node("Vec", |dump| {
+            for item in self {
+                item.dump(dump);
+            }
+        });
+    }
+
+    #[inline]
+    fn print(&self, printer: &mut Printer) {
+        for item in self {
+            item.print(printer);
+        }
+    }
+
+    #[inline]
+    fn grammar(grammar: &mut Grammar) -> GrammarExpr {
+        GrammarExpr::repeat(T::grammar(grammar))
+    }
+
+    #[inline]
+    fn generate(generator: &mut Generator) {
+        let count = generator.repeat();
+
+        generator.node("Vec", |generator| {
+            for _ in 0..count {
+                T::generate(generator);
+            }
+        });
+    }
+}
@@ -0,0 +1,83 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum StepToken {
+    #[token = "a"]
+    A,
+    #[token = "b"]
+    B,
+    #[token = "c"]
+    C,
+}
+
+type K = StepTokenKind;
+
+fn parser(source: &'static str) -> SkipWhitespace<CharsLexer<'static>, StepToken> {
+    SkipWhitespace::new(CharsLexer::new(source, SourcePath::Generated))
+}
+
+fn peek_offset(parser: &mut impl Parser<StepToken>) -> usize {
+    parser.peek().unwrap().unwrap().span.offset
+}
+
+#[test]
+fn rewind_over_peeked_token() {
+    let mut parser = parser("a  b c");
+    parser.next().unwrap();
+    assert_eq!(peek_offset(&mut parser), 3);
+
+    let prev_span = parser.prev_span();
+    let checkpoint = parser.checkpoint();
+
+    parser.next().unwrap();
+    let span = parser.span(0);
+    parser.add_expected(span, [K::A].iter().copied().collect());
+    assert_eq!(parser.prev_span().offset, 3);
+    assert!(!parser.expected_at(span).is_empty());
+
+    parser.rewind(checkpoint);
+
+    assert_eq!(parser.prev_span(), prev_span);
+    assert!(parser.expected_at(span).is_empty());
+    assert_eq!(peek_offset(&mut parser), 3);
+    assert_eq!(parser.next().unwrap().kind(), K::B);
+    assert_eq!(parser.prev_span().offset, 3);
+}
+
+#[test]
+fn failed_attempt_rewinds() {
+    let mut parser = parser("a b");
+    assert_eq!(peek_offset(&mut parser), 0);
+
+    let prev_span = parser.prev_span();
+
+    let result = parser.attempt(|parser| {
+        parser.next()?;
+        parser.next()?;
+
+        let span = parser.span(0);
+        parser.add_expected(span, TokenSet::FULL);
+
+        Err::<(), _>(Error::spanned(span, "rejected"))
+    });
+    let span = result.unwrap_err().span().unwrap();
+
+    assert_eq!(parser.prev_span(), prev_span);
+    assert!(parser.expected_at(span).is_empty());
+    assert_eq!(peek_offset(&mut parser), 0);
+    assert_eq!(parser.next().unwrap().kind(), K::A);
+}
+
+#[test]
+fn successful_attempt_keeps_position() {
+    let mut parser = parser("a b");
+    parser.peek().unwrap();
+
+    let kind = parser
+        .attempt(|parser| parser.next().map(|token| token.kind()))
+        .unwrap();
+
+    assert_eq!(kind, K::A);
+    assert_eq!(parser.prev_span().offset, 0);
+    assert_eq!(peek_offset(&mut parser), 2);
+}
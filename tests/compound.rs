@@ -0,0 +1,78 @@
+use std::rc::Rc;
+
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum CompoundToken {
+    #[token = "let"]
+    Let,
+    #[token = "x"]
+    X,
+    #[token = ";"]
+    Semi,
+}
+
+#[derive(Parse, Debug)]
+pub struct Binding {
+    pub keyword: Let,
+    pub name: X,
+}
+
+type K = CompoundTokenKind;
+
+/// A tuple whose first element may be missing, so that its start includes the second.
+type Statement = (Option<Let>, X, Semi);
+
+fn set(kinds: &[K]) -> TokenSet<K> {
+    kinds.iter().copied().collect()
+}
+
+#[test]
+fn rc_parses_like_its_inner_node() {
+    assert_eq!(<Rc<Binding>>::FIRST, Binding::FIRST);
+    assert_eq!(
+        [<Rc<Binding>>::NULLABLE, <Rc<Option<Binding>>>::NULLABLE],
+        [false, true]
+    );
+
+    let binding = parse_source::<Rc<Binding>>("let  x", SourcePath::Generated).unwrap();
+
+    assert_eq!(binding.name.span().offset, 5);
+    assert_eq!(dump_ast(&binding), dump_ast(&*binding));
+    assert_eq!(print_ast(&binding), "let x");
+}
+
+#[test]
+fn tuple_start_skips_nullable_first_element() {
+    assert_eq!(Statement::FIRST, set(&[K::Let, K::X]));
+    assert_eq!(
+        [Statement::NULLABLE, <(Option<Let>, Option<Semi>)>::NULLABLE],
+        [false, true]
+    );
+    assert_eq!(
+        <(Option<Let>, Option<Semi>)>::FIRST,
+        set(&[K::Let, K::Semi])
+    );
+}
+
+#[test]
+fn tuple_parses_with_and_without_its_first_element() {
+    let (keyword, name, _) = parse_source::<Statement>("x;", SourcePath::Generated).unwrap();
+
+    assert!(keyword.is_none());
+    assert_eq!(name.span().offset, 0);
+
+    let statement = parse_source::<Statement>("let x ;", SourcePath::Generated).unwrap();
+
+    assert!(statement.0.is_some());
+    assert_eq!(print_ast(&statement), "let x ;");
+    assert_eq!(
+        dump_ast(&statement),
+        concat!(
+            "Tuple @ 1:1..1:8\n",
+            "  Let \"let\" @ 1:1..1:4\n",
+            "  X \"x\" @ 1:5..1:6\n",
+            "  Semi \";\" @ 1:7..1:8\n",
+        )
+    );
+}
@@ -0,0 +1,134 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum GroupToken {
+    #[token = "{"]
+    OpenBrace,
+    #[token = "}"]
+    CloseBrace,
+    #[token = "|"]
+    Bar,
+    #[token = "="]
+    Equal,
+    #[token(description = "identifier")]
+    Ident(Ident),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ident {
+    span: Span,
+}
+
+impl Spanned for Ident {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Lex for Ident {
+    fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
+        let span = lexer.span(0);
+
+        while lexer.peek().map(|c| c.is_alphanumeric()).unwrap_or(false) {
+            lexer.consume();
+        }
+
+        let span = span | lexer.span(0);
+
+        if span.length == 0 {
+            return Err(Error::spanned(span, "expected identifier"));
+        }
+
+        Ok(Self { span })
+    }
+}
+
+#[derive(Parse, Debug)]
+#[token(GroupToken)]
+pub enum Group {
+    Group(Delimited<OpenBrace, Vec<Group>, CloseBrace>),
+    Ident(Ident),
+}
+
+type Braces = Delimited<OpenBrace, Vec<Group>, CloseBrace>;
+
+fn parser(source: &str) -> SkipWhitespace<CharsLexer<'_>, GroupToken> {
+    SkipWhitespace::new(CharsLexer::new(source, SourcePath::Generated))
+}
+
+fn next_offset(parser: &mut SkipWhitespace<CharsLexer<'_>, GroupToken>) -> Option<usize> {
+    parser.peek().unwrap().map(|token| token.span.offset)
+}
+
+#[test]
+fn nested_groups() {
+    let group = parse_source::<Braces>("{ a { b { } } c }", SourcePath::Generated).unwrap();
+
+    assert_eq!(group.span().offset, 0);
+    assert_eq!(group.span().length, 17);
+    assert_eq!(group.inner.len(), 3);
+
+    match &group.inner[1] {
+        Group::Group(inner) => {
+            assert_eq!(inner.span().offset, 4);
+            assert_eq!(inner.span().length, 9);
+        }
+        group => panic!("expected a group, found {:?}", group),
+    }
+}
+
+#[test]
+fn unclosed_group() {
+    let error = parse_source::<Braces>("{ a { b }", SourcePath::Generated).unwrap_err();
+
+    assert_eq!(error.msg(), "unclosed delimiter");
+    assert_eq!(error.span().unwrap().offset, 0);
+    assert!(error.hints()[0].msg().ends_with("found eof"));
+}
+
+#[test]
+fn recovers_after_an_error_inside_a_group() {
+    let mut parser = parser("{ a = { b } c } d");
+    let error = parser.parse::<Braces>().unwrap_err();
+
+    assert_eq!(error.span().unwrap().offset, 4);
+    assert_eq!(error.msg(), "expected one of `{`, `}`, identifier");
+    assert_eq!(next_offset(&mut parser), Some(16));
+}
+
+#[test]
+fn error_inside_an_unclosed_group() {
+    let errors = parse_source_with_diagnostics::<Braces>("{ a = { b }", SourcePath::Generated)
+        .err()
+        .unwrap();
+    let msgs = errors
+        .iter()
+        .map(|error| (error.span().unwrap().offset, error.msg()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(msgs.len(), 2);
+    assert_eq!(msgs[0].0, 0);
+    assert_eq!(msgs[0].1, "unclosed delimiter");
+    assert_eq!(msgs[1].0, 4);
+}
+
+#[test]
+fn skips_nested_groups() {
+    let mut parser = parser("{ a = { b } c } d");
+    let open = parser.parse::<OpenBrace>().unwrap();
+    let close = Braces::skip_to_close(&mut parser, open.span()).unwrap();
+
+    assert_eq!(close.span().offset, 14);
+    assert_eq!(next_offset(&mut parser), Some(16));
+}
+
+#[test]
+fn skips_to_the_next_close_with_the_same_kind_as_open() {
+    let mut parser = parser("| a = b | c");
+    let open = parser.parse::<Bar>().unwrap();
+    let close = Delimited::<Bar, Vec<Ident>, Bar>::skip_to_close(&mut parser, open.span()).unwrap();
+
+    assert_eq!(close.span().offset, 8);
+    assert_eq!(next_offset(&mut parser), Some(10));
+}
@@ -0,0 +1,93 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum OldToken {
+    #[token = "var"]
+    Var,
+    #[token = "let"]
+    Let,
+    #[token = "const"]
+    Const,
+    #[token = ";"]
+    Semi,
+}
+
+#[derive(Parse, Debug)]
+pub enum Statement {
+    #[parse(deprecated = "use `let` instead")]
+    Var(Var, Semi),
+    #[parse(deprecated)]
+    Const(Const, Semi),
+    Let(Let, Semi),
+}
+
+fn diagnostics(source: &str) -> Vec<Error> {
+    parse_source_with_diagnostics::<Vec<Statement>>(source, SourcePath::Generated)
+        .unwrap()
+        .diagnostics
+        .into_vec()
+}
+
+#[test]
+fn deprecated_variant_warns() {
+    let diagnostics = diagnostics("let; var;");
+
+    assert_eq!(diagnostics.len(), 1);
+
+    let warning = &diagnostics[0];
+    assert_eq!(warning.msg(), "use of deprecated 'Var'");
+    assert_eq!(warning.severity(), Severity::Warning);
+    assert_eq!(warning.span().unwrap().column, 5);
+    assert_eq!(warning.span().unwrap().length, 4);
+
+    let hints = warning
+        .hints()
+        .iter()
+        .map(ErrorHint::msg)
+        .collect::<Vec<_>>();
+    assert_eq!(hints, ["use `let` instead"]);
+}
+
+#[test]
+fn deprecated_variant_without_note() {
+    let diagnostics = diagnostics("const;");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].msg(), "use of deprecated 'Const'");
+    assert!(diagnostics[0].hints().is_empty());
+}
+
+#[test]
+fn no_warnings_without_deprecated_variants() {
+    assert!(diagnostics("let; let;").is_empty());
+}
+
+#[test]
+fn failure_returns_earlier_diagnostics() {
+    let errors = parse_source_with_diagnostics::<Vec<Statement>>("var; let", SourcePath::Generated)
+        .unwrap_err();
+
+    assert!(errors.has_errors());
+
+    let severities = errors.iter().map(Error::severity).collect::<Vec<_>>();
+    assert_eq!(severities, [Severity::Warning, Severity::Error]);
+}
+
+#[test]
+fn rewind_discards_diagnostics() {
+    let lexer = CharsLexer::new("var; let;", SourcePath::Generated);
+    let mut parser = SkipWhitespace::<_, OldToken>::new(lexer);
+
+    let result = parser.attempt(|parser| {
+        parser.parse::<Statement>()?;
+
+        Err::<(), _>(Error::new("rejected"))
+    });
+    assert!(result.is_err());
+    assert!(parser.take_diagnostics().is_empty());
+
+    parser
+        .attempt(|parser| parser.parse::<Statement>())
+        .unwrap();
+    assert_eq!(parser.take_diagnostics().len(), 1);
+}
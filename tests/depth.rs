@@ -0,0 +1,74 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum NestToken {
+    #[token = "["]
+    Open,
+    #[token = "]"]
+    Close,
+    #[token = "x"]
+    X,
+}
+
+#[derive(Parse, Debug)]
+pub enum Nest {
+    Group(Group),
+    X(X),
+}
+
+#[derive(Parse, Debug)]
+pub struct Group {
+    pub open: Open,
+    pub inner: Box<Nest>,
+    pub close: Close,
+}
+
+fn nested(depth: usize) -> String {
+    format!("{}x{}", "[".repeat(depth), "]".repeat(depth))
+}
+
+fn parse(source: &str, max_depth: Option<usize>) -> Result<Nest, Error> {
+    let lexer = CharsLexer::new(source, SourcePath::Generated);
+    let mut parser = SkipWhitespace::<_, NestToken>::new(lexer);
+
+    if let Some(max_depth) = max_depth {
+        parser = parser.with_max_depth(max_depth);
+    }
+
+    parser.parse::<Nest>()
+}
+
+#[test]
+fn shallow_input_parses() {
+    // Every level of brackets is two derived nodes, `Nest` and `Group`.
+    assert!(parse(&nested(DEFAULT_MAX_DEPTH / 2 - 1), None).is_ok());
+}
+
+#[test]
+fn deep_input_errors() {
+    let err = parse(&nested(100_000), None).unwrap_err();
+
+    assert_eq!(err.msg(), "nesting too deep");
+}
+
+#[test]
+fn max_depth_counts_derived_nodes() {
+    assert!(parse(&nested(4), Some(9)).is_ok());
+    assert_eq!(
+        parse(&nested(4), Some(8)).unwrap_err().msg(),
+        "nesting too deep"
+    );
+}
+
+#[cfg(feature = "grow-stack")]
+#[test]
+fn deep_input_parses_with_stack_growth() {
+    let source = nested(100_000);
+    let lexer = CharsLexer::new(&source, SourcePath::Generated);
+    let mut parser = SkipWhitespace::<_, NestToken>::new(lexer).with_stack_growth();
+
+    let nest = parser.parse::<Nest>().unwrap();
+
+    // Dropping the tree recurses as deep as it is nested.
+    std::mem::forget(nest);
+}
@@ -0,0 +1,35 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum DumpToken {
+    #[token = ";"]
+    Semi,
+    #[token = ","]
+    Comma,
+}
+
+/// Implemented by hand, without overriding [`Parse::dump`].
+#[derive(Debug)]
+pub struct Opaque<T>(pub T);
+
+impl Parse for Opaque<Semi> {
+    type Token = DumpToken;
+
+    const FIRST: ParseFirst<DumpToken> = Semi::FIRST;
+
+    fn parse(parser: &mut impl Parser<DumpToken>) -> Result<Self, Error> {
+        Semi::parse(parser).map(Opaque)
+    }
+}
+
+#[derive(Parse, Debug)]
+pub struct Statement {
+    pub inner: Opaque<Semi>,
+}
+
+#[test]
+fn default_dump_is_named_without_paths() {
+    let statement = parse_source::<Statement>(";", SourcePath::Generated).unwrap();
+
+    assert_eq!(dump_ast(&statement), "Statement\n  inner: Opaque<Semi>\n");
+}
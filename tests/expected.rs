@@ -0,0 +1,119 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum ExprToken {
+    #[token = "let"]
+    Let,
+    #[token = "="]
+    Equal,
+    #[token = ";"]
+    Semi,
+    #[token(description = "identifier")]
+    Ident(Ident),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ident {
+    span: Span,
+}
+
+impl Spanned for Ident {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Lex for Ident {
+    fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
+        let span = lexer.span(0);
+
+        while lexer.peek().map(|c| c.is_alphanumeric()).unwrap_or(false) {
+            lexer.consume();
+        }
+
+        let span = span | lexer.span(0);
+
+        if span.length == 0 {
+            return Err(Error::spanned(span, "expected identifier"));
+        }
+
+        Ok(Self { span })
+    }
+}
+
+#[derive(Parse, Debug)]
+pub struct Binding {
+    pub name: Ident,
+    pub value: Option<(Equal, Ident)>,
+    pub semi: Semi,
+}
+
+#[derive(Parse, Debug)]
+pub enum Statement {
+    Let(Let, Box<Binding>),
+    Expr(Ident, Semi),
+}
+
+fn error<P>(source: &str) -> Error
+where
+    P: Parse<Token = ExprToken> + std::fmt::Debug,
+{
+    parse_source::<P>(source, SourcePath::Generated).unwrap_err()
+}
+
+#[test]
+fn alternatives_of_an_enum() {
+    let error = error::<Statement>(";");
+
+    assert_eq!(error.msg(), "expected one of `let`, identifier");
+    assert_eq!(error.hints()[0].msg(), "found `;`");
+    assert_eq!(error.node(), Some("Statement"));
+}
+
+#[test]
+fn optional_items_merge_with_the_next_item() {
+    let error = error::<Statement>("let a b");
+
+    assert_eq!(error.msg(), "expected one of `=`, `;`");
+    assert_eq!(error.span().unwrap().offset, 6);
+    assert_eq!(error.hints()[0].msg(), "found identifier");
+}
+
+#[test]
+fn a_single_alternative() {
+    assert_eq!(error::<Statement>("a =").msg(), "expected `;`");
+}
+
+#[test]
+fn expected_tokens_merge_with_eof() {
+    let error = error::<Vec<Statement>>("a; =");
+
+    assert_eq!(error.msg(), "expected one of `let`, identifier or eof");
+    assert_eq!(error.span().unwrap().offset, 3);
+}
+
+#[test]
+fn earlier_expectations_are_dropped() {
+    // `=` and `;` were expected after `a`, but the error is further on
+    let error = error::<Vec<Statement>>("let a = b let");
+
+    assert_eq!(error.msg(), "expected `;`");
+    assert_eq!(error.span().unwrap().offset, 10);
+}
+
+#[test]
+fn innermost_node_is_reported() {
+    let error = error::<Statement>("let a = ;");
+
+    assert_eq!(error.node(), Some("Binding"));
+    assert!(error.to_string().contains("while parsing Binding\n"));
+}
+
+#[test]
+fn full_set_reports_only_found() {
+    let span = error::<Statement>(";").span().unwrap();
+    let error = Error::expected_one(span, TokenSet::<ExprTokenKind>::FULL, "`=`");
+
+    assert_eq!(error.msg(), "unexpected `=`");
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "fuzz")]
+
+use lasagna::{fuzz::Corpus, *};
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum JsonToken {
+    #[token = "{"]
+    OpenBrace,
+    #[token = "}"]
+    CloseBrace,
+    #[token = "="]
+    Equal,
+    #[token]
+    LitStr(LitStr),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LitStr {
+    span: Span,
+}
+
+impl Spanned for LitStr {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Lex for LitStr {
+    fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
+        let span = lexer.span(0);
+
+        lexer.expect('"')?;
+
+        loop {
+            match lexer.next() {
+                Some('"') => break,
+                Some(_) => {}
+                None => return Err(Error::spanned(span, "expected end to string")),
+            }
+        }
+
+        Ok(Self {
+            span: span | lexer.span(0),
+        })
+    }
+}
+
+#[derive(Spanned, Parse, Debug)]
+pub struct Statement {
+    pub key: LitStr,
+    pub equal: Equal,
+}
+
+#[derive(Spanned, Parse, Debug)]
+pub enum Value {
+    Braces { open: OpenBrace, close: CloseBrace },
+    Str(LitStr),
+}
+
+const CORPUS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus/json");
+
+#[test]
+fn lex_corpus() {
+    Corpus::new(CORPUS).run(fuzz::lex::<JsonToken>);
+}
+
+#[test]
+fn parse_corpus() {
+    Corpus::new(CORPUS).run(|data| {
+        fuzz::parse::<Statement>(data);
+        fuzz::parse::<Value>(data);
+    });
+}
+
+#[test]
+fn corpus_reports_failing_input() {
+    let failure = Corpus::new(CORPUS)
+        .mutations(0)
+        .check(|data| {
+            if data.contains(&b'=') {
+                panic!("found an equal sign");
+            }
+        })
+        .unwrap_err();
+
+    assert!(failure.input.unwrap().contains(&b'='));
+    assert_eq!(failure.message, "found an equal sign");
+}
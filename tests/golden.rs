@@ -0,0 +1,56 @@
+#![cfg(feature = "testing")]
+
+use lasagna::{testing::Golden, *};
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum JsonToken {
+    #[token = "="]
+    Equal,
+    #[token]
+    LitStr(LitStr),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LitStr {
+    span: Span,
+}
+
+impl Spanned for LitStr {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Lex for LitStr {
+    fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
+        let span = lexer.span(0);
+
+        lexer.expect('"')?;
+
+        loop {
+            match lexer.next() {
+                Some('"') => break,
+                Some(_) => {}
+                None => return Err(Error::spanned(span, "expected end to string")),
+            }
+        }
+
+        Ok(Self {
+            span: span | lexer.span(0),
+        })
+    }
+}
+
+#[derive(Spanned, Parse, Debug)]
+pub struct Statement {
+    pub key: LitStr,
+    pub equal: Equal,
+}
+
+const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+#[test]
+fn golden() {
+    Golden::new(GOLDEN).run::<Statement>();
+}
@@ -0,0 +1,104 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum GrammarToken {
+    #[token = "="]
+    Equal,
+    #[token = ";"]
+    Semi,
+    #[token = "'\""]
+    Quotes,
+    #[token(description = "identifier")]
+    Ident(Ident),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ident {
+    span: Span,
+}
+
+impl Spanned for Ident {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Lex for Ident {
+    fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
+        let span = lexer.span(0);
+
+        while lexer.peek().map(|c| c.is_alphanumeric()).unwrap_or(false) {
+            lexer.consume();
+        }
+
+        let span = span | lexer.span(0);
+
+        if span.length == 0 {
+            return Err(Error::spanned(span, "expected identifier"));
+        }
+
+        Ok(Self { span })
+    }
+}
+
+#[derive(Parse, Debug)]
+pub struct Entry {
+    pub key: Ident,
+    pub equal: Equal,
+    pub value: Option<Ident>,
+    pub end: End<Semi>,
+}
+
+/// Instantiated with two different terminators, which must be different rules.
+#[derive(Parse, Debug)]
+pub struct End<T: Parse<Token = GrammarToken>> {
+    pub terminator: T,
+}
+
+#[derive(Parse, Debug)]
+pub enum Statement {
+    Entry(Entry),
+    Quoted(Ident, End<Quotes>),
+}
+
+#[test]
+fn ebnf() {
+    assert_eq!(
+        Grammar::of::<Statement>().to_ebnf(),
+        concat!(
+            "Statement = Entry | ( Ident , End_Quotes ) ;\n",
+            "Entry = Ident , \"=\" , [ Ident ] , End_Semi ;\n",
+            "End_Semi = \";\" ;\n",
+            "End_Quotes = \"'\" , '\"' ;\n",
+        )
+    );
+}
+
+#[test]
+fn generic_instantiations_are_different_rules() {
+    let grammar = Grammar::of::<Statement>();
+    let names = grammar
+        .rules()
+        .iter()
+        .map(|rule| rule.name.as_str())
+        .collect::<Vec<_>>();
+
+    assert_eq!(names, ["Statement", "Entry", "End<Semi>", "End<Quotes>"]);
+    assert_eq!(
+        grammar.get("End<Quotes>").unwrap().expr,
+        GrammarExpr::Sequence(vec![GrammarExpr::Terminal(String::from("'\""))])
+    );
+}
+
+#[test]
+fn svg() {
+    let svg = Grammar::of::<Statement>().to_svg();
+
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches(r#"<text class="rule""#).count(), 4);
+    assert!(svg.contains(">End&lt;Quotes&gt;</text>"));
+    assert!(svg.contains(">&quot;'&quot;&quot;</text>"));
+    assert!(!svg.contains("End<"));
+}
@@ -0,0 +1,87 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum HighlightToken {
+    #[token = "let"]
+    #[token(class = "keyword")]
+    Let,
+    #[token = "<"]
+    #[token(class = "operator")]
+    Less,
+    #[token = ";"]
+    Semi,
+    #[token(class = "string")]
+    Str(Str),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Str {
+    span: Span,
+}
+
+impl Spanned for Str {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Lex for Str {
+    fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
+        let span = lexer.span(0);
+
+        lexer.expect('"')?;
+
+        while lexer.peek().map(|&c| c != '"').unwrap_or(false) {
+            lexer.consume();
+        }
+
+        lexer.expect('"')?;
+
+        Ok(Self {
+            span: span | lexer.span(0),
+        })
+    }
+}
+
+const SOURCE: &str = "let \"a&b\" < ;  @@";
+
+fn highlights() -> Vec<Highlight> {
+    let mut lexer = CharsLexer::new(SOURCE, SourcePath::Generated);
+
+    highlight::<HighlightToken>(&mut lexer)
+}
+
+#[test]
+fn classifies_tokens_and_lex_errors() {
+    let highlights = highlights();
+    let texts = highlights.iter().map(|h| h.span.text()).collect::<Vec<_>>();
+    let classes = highlights.iter().map(|h| h.class).collect::<Vec<_>>();
+
+    assert_eq!(texts, ["let", "\"a&b\"", "<", "@@"]);
+    assert_eq!(classes, ["keyword", "string", "operator", ERROR_CLASS]);
+}
+
+#[test]
+fn render_ansi_output() {
+    assert_eq!(
+        render_ansi(SOURCE, &highlights()),
+        "\x1b[1;35mlet\x1b[0m \x1b[32m\"a&b\"\x1b[0m \x1b[37m<\x1b[0m ;  \x1b[4;31m@@\x1b[0m"
+    );
+}
+
+#[test]
+fn render_html_output() {
+    assert_eq!(
+        render_html(SOURCE, &highlights()),
+        "<span class=\"tok-keyword\">let</span> \
+         <span class=\"tok-string\">&quot;a&amp;b&quot;</span> \
+         <span class=\"tok-operator\">&lt;</span> ;  \
+         <span class=\"tok-error\">@@</span>"
+    );
+}
+
+#[test]
+fn render_html_escapes_unclassified_text() {
+    assert_eq!(render_html("'<>'", &[]), "&#39;&lt;&gt;&#39;");
+}
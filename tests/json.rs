@@ -0,0 +1,60 @@
+#![cfg(feature = "json")]
+
+use lasagna::*;
+use serde_json::json;
+
+fn spans() -> (Span, Span) {
+    let mut lexer = CharsLexer::new("a = b", SourcePath::Generated);
+    let key = lexer.span(1);
+
+    for _ in 0..4 {
+        lexer.consume();
+    }
+
+    (key, lexer.span(1))
+}
+
+#[test]
+fn rustc_diagnostic() {
+    let (key, value) = spans();
+    let error = Error::spanned(value, "unknown value")
+        .with_code("E0001")
+        .while_parsing("Statement")
+        .with_hint(ErrorHint::spanned(key, "assigned to this key"))
+        .with_suggestion(Suggestion::insert(value, "\"", "quote the value"));
+
+    let expected: serde_json::Value =
+        serde_json::from_str(include_str!("json/diagnostic.json")).unwrap();
+
+    assert_eq!(json::diagnostic(&error), expected);
+}
+
+#[test]
+fn missing_code_is_null() {
+    let (key, _) = spans();
+    let error = Error::spanned(key, "unknown key").with_severity(Severity::Warning);
+    let diagnostic = json::diagnostic(&error);
+
+    assert_eq!(diagnostic["code"], json!(null));
+    assert_eq!(diagnostic["level"], "warning");
+    assert_eq!(diagnostic["children"], json!([]));
+}
+
+#[test]
+fn one_diagnostic_per_line() {
+    let (key, value) = spans();
+    let mut errors = Errors::new();
+    errors.push(Error::spanned(value, "second"));
+    errors.push(Error::spanned(key, "first"));
+
+    let mut output = Vec::new();
+    json::write_diagnostics(&mut output, &errors).unwrap();
+
+    let messages = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["message"].clone())
+        .collect::<Vec<_>>();
+
+    assert_eq!(messages, [json!("first"), json!("second")]);
+}
@@ -0,0 +1,215 @@
+#![cfg(feature = "lsp")]
+
+use std::io::{BufRead, BufReader, Read};
+
+use lasagna::*;
+use serde_json::{json, Value};
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigToken {
+    #[token = "="]
+    #[token(class = "operator")]
+    Equal,
+    #[token = ";"]
+    Semi,
+    #[token(class = "variable", description = "identifier")]
+    Ident(Ident),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ident {
+    span: Span,
+}
+
+impl Spanned for Ident {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Lex for Ident {
+    fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
+        let span = lexer.span(0);
+
+        while lexer.peek().map(|c| c.is_alphanumeric()).unwrap_or(false) {
+            lexer.consume();
+        }
+
+        let span = span | lexer.span(0);
+
+        if span.length == 0 {
+            return Err(Error::spanned(span, "expected identifier"));
+        }
+
+        Ok(Self { span })
+    }
+}
+
+#[derive(Spanned, Parse, Debug)]
+#[parse(symbol = "key")]
+pub struct Entry {
+    #[parse(name)]
+    pub key: Ident,
+    pub equal: Equal,
+    pub value: Ident,
+    pub semi: Semi,
+}
+
+const URI: &str = "file:///config";
+
+fn frame(messages: &[Value]) -> Vec<u8> {
+    let mut input = Vec::new();
+
+    for message in messages {
+        let body = message.to_string();
+        input.extend(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).bytes());
+    }
+
+    input
+}
+
+fn unframe(output: &[u8]) -> Vec<Value> {
+    let mut reader = BufReader::new(output);
+    let mut messages = Vec::new();
+
+    loop {
+        let mut header = String::new();
+
+        if reader.read_line(&mut header).unwrap() == 0 {
+            return messages;
+        }
+
+        let length = header
+            .trim_end()
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+
+        reader.read_line(&mut String::new()).unwrap();
+
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        messages.push(serde_json::from_slice(&body).unwrap());
+    }
+}
+
+fn run(messages: &[Value]) -> (lsp::Server<Entry>, Vec<Value>) {
+    let mut server = lsp::Server::<Entry>::new();
+    let mut output = Vec::new();
+
+    server.run(&frame(messages)[..], &mut output).unwrap();
+
+    (server, unframe(&output))
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+#[test]
+fn diagnostics_and_semantic_tokens() {
+    let (server, output) = run(&[
+        request(1, "initialize", json!({})),
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": URI, "text": "a = ;" } }),
+        ),
+        notification(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": URI },
+                "contentChanges": [{ "text": "a = b;" }],
+            }),
+        ),
+        request(
+            2,
+            "textDocument/semanticTokens/full",
+            json!({ "textDocument": { "uri": URI } }),
+        ),
+        notification("exit", Value::Null),
+    ]);
+
+    assert_eq!(server.document(URI), Some("a = b;"));
+    assert_eq!(output.len(), 4);
+
+    let legend = &output[0]["result"]["capabilities"]["semanticTokensProvider"]["legend"];
+    assert_eq!(output[0]["id"], 1);
+    assert_eq!(legend["tokenTypes"], json!(["operator", "variable"]));
+
+    assert_eq!(output[1]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(output[1]["params"]["uri"], URI);
+
+    let diagnostics = output[1]["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({
+            "start": { "line": 0, "character": 4 },
+            "end": { "line": 0, "character": 5 },
+        })
+    );
+
+    assert_eq!(output[2]["params"]["diagnostics"], json!([]));
+
+    assert_eq!(output[3]["id"], 2);
+    assert_eq!(
+        output[3]["result"]["data"],
+        json!([0, 0, 1, 1, 0, 0, 2, 1, 0, 0, 0, 2, 1, 1, 0])
+    );
+}
+
+#[test]
+fn document_symbols() {
+    let (_, output) = run(&[
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": URI, "text": "key = value;" } }),
+        ),
+        request(
+            1,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": URI } }),
+        ),
+    ]);
+
+    let symbols = output[1]["result"].as_array().unwrap();
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0]["name"], "key");
+    assert_eq!(symbols[0]["kind"], 20);
+}
+
+#[test]
+fn diagnostic_code_is_omitted_without_one() {
+    let error = parse_source::<Entry>("a = ;", SourcePath::Generated).unwrap_err();
+    let diagnostic = lsp::diagnostic(&error, URI);
+
+    assert!(diagnostic.get("code").is_none());
+    assert_eq!(diagnostic["severity"], 1);
+
+    let diagnostic = lsp::diagnostic(
+        &error.with_code("E0001").with_severity(Severity::Warning),
+        URI,
+    );
+
+    assert_eq!(diagnostic["code"], "E0001");
+    assert_eq!(diagnostic["severity"], 2);
+}
+
+#[test]
+fn client_responses_get_no_reply() {
+    let (_, output) = run(&[
+        json!({ "jsonrpc": "2.0", "id": 7, "result": null }),
+        json!({ "jsonrpc": "2.0", "id": 8, "error": { "code": -32601, "message": "no" } }),
+        request(1, "shutdown", Value::Null),
+    ]);
+
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0]["id"], 1);
+    assert!(output[0].get("error").is_none());
+}
@@ -0,0 +1,104 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum Tok {
+    #[token = "x"]
+    X,
+    #[token]
+    Bang(Bang),
+}
+
+/// Lexes in front of a `!` without consuming it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bang {
+    span: Span,
+}
+
+impl Spanned for Bang {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Lex for Bang {
+    fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
+        let span = lexer.span(0);
+
+        match lexer.peek() {
+            Some('!') => Ok(Self { span }),
+            _ => Err(Error::spanned(span, "expected '!'")),
+        }
+    }
+}
+
+/// Always parses without consuming a token.
+#[derive(Debug)]
+pub struct Nothing;
+
+impl Parse for Nothing {
+    type Token = Tok;
+
+    const FIRST: ParseFirst<Self::Token> = TokenSet::FULL;
+
+    const NULLABLE: bool = true;
+
+    fn parse(_: &mut impl Parser<Self::Token>) -> Result<Self, Error> {
+        Ok(Self)
+    }
+}
+
+/// Parses all of `source` as `P`, returning no progress as an error in every build.
+fn parse<P: Parse<Token = Tok>>(source: &'static str) -> Result<P, Error> {
+    let lexer = CharsLexer::new_static(source, SourcePath::Generated);
+    let mut parser = SkipWhitespace::new(lexer).with_no_progress_panics(false);
+
+    parser.parse::<P>()
+}
+
+#[test]
+fn repetition_without_progress_errors() {
+    let err = parse::<Vec<Nothing>>("x").unwrap_err();
+
+    assert!(
+        err.msg()
+            .contains("was parsed without consuming input in a repetition"),
+        "{}",
+        err
+    );
+    assert_eq!(err.span().unwrap().offset, 0);
+}
+
+#[test]
+fn repetition_with_progress_parses() {
+    let items = parse::<Vec<X>>("x x x").unwrap();
+
+    assert_eq!(items.len(), 3);
+}
+
+#[test]
+fn lexer_without_progress_errors() {
+    let err = parse::<Vec<X>>("x !").unwrap_err();
+
+    assert!(
+        err.msg()
+            .contains("returned a token without consuming input"),
+        "{}",
+        err
+    );
+    assert_eq!(err.span().unwrap().offset, 2);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "was parsed without consuming input in a repetition")]
+fn repetition_without_progress_panics_in_debug_builds() {
+    let _ = parse_source::<Vec<Nothing>>("x", SourcePath::Generated);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "returned a token without consuming input")]
+fn lexer_without_progress_panics_in_debug_builds() {
+    let _ = parse_source::<Vec<X>>("x !", SourcePath::Generated);
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "testing")]
+
+use lasagna::{
+    testing::{RoundTrip, RoundTripFailureKind},
+    *,
+};
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum ConfigToken {
+    #[token = "="]
+    Equal,
+    #[token = ";"]
+    Semi,
+    #[token(description = "identifier", sample = "a", sample = "b")]
+    Ident(Ident),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ident {
+    span: Span,
+}
+
+impl Spanned for Ident {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Lex for Ident {
+    fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
+        let span = lexer.span(0);
+
+        while lexer.peek().map(|c| c.is_alphanumeric()).unwrap_or(false) {
+            lexer.consume();
+        }
+
+        let span = span | lexer.span(0);
+
+        if span.length == 0 {
+            return Err(Error::spanned(span, "expected identifier"));
+        }
+
+        Ok(Self { span })
+    }
+}
+
+#[derive(Parse, Debug)]
+pub struct Entry {
+    pub key: Ident,
+    pub equal: Equal,
+    pub value: Option<Ident>,
+    pub semi: Semi,
+}
+
+/// Ambiguous, a single identifier parses as `first` but may have been generated as `second`.
+#[derive(Parse, Debug)]
+pub struct Pair {
+    pub first: Option<Ident>,
+    pub second: Option<Ident>,
+    pub semi: Semi,
+}
+
+#[test]
+fn unambiguous_grammar_round_trips() {
+    let generated = RoundTrip::new(64).check::<Vec<Entry>>().unwrap();
+
+    assert_eq!(generated, 64);
+}
+
+#[test]
+fn ambiguous_grammar_is_caught() {
+    let failure = RoundTrip::new(64).check::<Pair>().unwrap_err();
+
+    match failure.kind {
+        RoundTripFailureKind::Ambiguous {
+            ref generated,
+            ref parsed,
+        } => {
+            assert!(generated.contains("first: None"), "{}", failure);
+            assert!(parsed.contains("second: None"), "{}", failure);
+        }
+        _ => panic!("expected an ambiguity, got {}", failure),
+    }
+}
+
+#[test]
+fn generator_records_the_generated_tree() {
+    let mut generator = Generator::new(3);
+    Entry::generate(&mut generator);
+    let generated = generator.finish().unwrap();
+
+    let node = parse_source::<Entry>(&generated.source, SourcePath::Generated).unwrap();
+    let mut dump = AstDump::without_spans();
+    node.dump(&mut dump);
+
+    assert_eq!(generated.structure, dump.finish());
+    assert_eq!(print_ast(&node), generated.source);
+}
@@ -0,0 +1,103 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum SpanToken {
+    #[token = "let"]
+    Let,
+    #[token = ";"]
+    Semi,
+    #[token(description = "identifier")]
+    Ident(Ident),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ident {
+    span: Span,
+}
+
+impl Spanned for Ident {
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Lex for Ident {
+    fn lex(lexer: &mut impl Lexer<Output = char>) -> Result<Self, Error> {
+        let span = lexer.span(0);
+
+        while lexer.peek().map(|c| c.is_alphanumeric()).unwrap_or(false) {
+            lexer.consume();
+        }
+
+        let span = span | lexer.span(0);
+
+        if span.length == 0 {
+            return Err(Error::spanned(span, "expected identifier"));
+        }
+
+        Ok(Self { span })
+    }
+}
+
+fn skip_whitespace(source: &'static str) -> SkipWhitespace<CharsLexer<'static>, SpanToken> {
+    SkipWhitespace::new(CharsLexer::new(source, SourcePath::Generated))
+}
+
+/// Returns the offset, length, line and column of `span`, lines and columns counting from zero.
+fn pos(span: Span) -> (usize, usize, usize, usize) {
+    (span.offset, span.length, span.line, span.column)
+}
+
+#[test]
+fn token_spans_exclude_whitespace() {
+    let mut parser = skip_whitespace("  let\n\t x  ;");
+
+    assert_eq!(pos(parser.next().unwrap().span), (2, 3, 0, 2));
+    assert_eq!(pos(parser.next().unwrap().span), (8, 1, 1, 2));
+    assert_eq!(pos(parser.next().unwrap().span), (11, 1, 1, 5));
+}
+
+#[test]
+fn prev_span_after_next() {
+    let mut parser = skip_whitespace("  let x");
+    assert_eq!(pos(parser.prev_span()), (0, 0, 0, 0));
+
+    parser.next().unwrap();
+    assert_eq!(pos(parser.prev_span()), (2, 3, 0, 2));
+
+    parser.next().unwrap();
+    assert_eq!(pos(parser.prev_span()), (6, 1, 0, 6));
+}
+
+#[test]
+fn peek_keeps_prev_span() {
+    let mut parser = skip_whitespace("let  x");
+    parser.next().unwrap();
+
+    assert_eq!(pos(parser.peek().unwrap().unwrap().span), (5, 1, 0, 5));
+    assert_eq!(pos(parser.prev_span()), (0, 3, 0, 0));
+}
+
+#[test]
+fn prev_span_at_eof() {
+    let mut parser = skip_whitespace("let ;  \n");
+    parser.next().unwrap();
+    parser.next().unwrap();
+
+    assert!(parser.peek().unwrap().is_none());
+    assert!(parser.is_empty());
+    assert_eq!(pos(parser.prev_span()), (4, 1, 0, 4));
+
+    let mut empty = skip_whitespace("   ");
+    assert!(empty.peek().unwrap().is_none());
+    assert_eq!(pos(empty.prev_span()), (0, 0, 0, 0));
+}
+
+#[test]
+fn errors_use_the_token_span() {
+    let error = parse_source::<Let>("   x", SourcePath::Generated).unwrap_err();
+
+    assert_eq!(pos(error.span().unwrap()), (3, 1, 0, 3));
+    assert_eq!(pos(error.hints()[0].span().unwrap()), (3, 1, 0, 3));
+}
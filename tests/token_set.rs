@@ -0,0 +1,98 @@
+use lasagna::*;
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum SetToken {
+    #[token = "{"]
+    OpenBrace,
+    #[token = "}"]
+    CloseBrace,
+    #[token = "="]
+    Equal,
+    #[token = ";"]
+    Semi,
+}
+
+/// Recursive through its second field, which is only reached if the first is nullable.
+#[derive(Parse, Debug)]
+pub enum Tree {
+    Node(OpenBrace, Vec<Tree>, CloseBrace),
+    Leaf(Semi),
+}
+
+#[derive(Parse, Debug)]
+pub struct Assign {
+    pub equal: Option<Equal>,
+    pub value: Tree,
+}
+
+#[derive(Parse, Debug)]
+pub struct Empty {
+    pub equal: Option<Equal>,
+    pub semi: Option<Semi>,
+}
+
+type K = SetTokenKind;
+
+fn set(kinds: &[K]) -> TokenSet<K> {
+    kinds.iter().copied().collect()
+}
+
+#[test]
+fn set_operations() {
+    let a = set(&[K::OpenBrace, K::Equal]);
+    let b = set(&[K::Equal, K::Semi]);
+
+    assert_eq!(a | b, set(&[K::OpenBrace, K::Equal, K::Semi]));
+    assert_eq!(a & b, set(&[K::Equal]));
+    assert_eq!(a - b, set(&[K::OpenBrace]));
+    assert_eq!(a.union(b), a | b);
+    assert_eq!(TokenSet::EMPTY.with(K::Equal.index()), set(&[K::Equal]));
+    assert!((a - a).is_empty());
+}
+
+#[test]
+fn membership_and_iteration() {
+    let a = set(&[K::Semi, K::OpenBrace]);
+
+    assert!(a.contains(&K::Semi));
+    assert!(!a.contains(&K::Equal));
+    assert_eq!(a.iter().collect::<Vec<_>>(), [K::OpenBrace, K::Semi]);
+    assert_eq!(a.len(), 2);
+    assert!(!a.contains_index(TokenSet::<K>::CAPACITY));
+    assert_eq!(TokenSet::<K>::FULL.len(), K::ALL.len());
+    assert!(TokenSet::<K>::EMPTY.is_empty());
+}
+
+#[test]
+fn first_sets_are_constants() {
+    const TREE: TokenSet<K> = Tree::FIRST;
+    const ASSIGN: TokenSet<K> = Assign::FIRST;
+
+    assert_eq!(TREE, set(&[K::OpenBrace, K::Semi]));
+    assert_eq!(ASSIGN, set(&[K::OpenBrace, K::Equal, K::Semi]));
+    assert_eq!(
+        <(Option<Equal>, Semi, CloseBrace)>::FIRST,
+        set(&[K::Equal, K::Semi])
+    );
+}
+
+#[test]
+fn nullable_is_a_constant() {
+    const NULLABLE: [bool; 5] = [
+        Tree::NULLABLE,
+        Empty::NULLABLE,
+        Assign::NULLABLE,
+        <Vec<Tree>>::NULLABLE,
+        <(Option<Equal>, Vec<Semi>)>::NULLABLE,
+    ];
+
+    assert_eq!(NULLABLE, [false, true, false, true, true]);
+    assert_eq!(Empty::FIRST, set(&[K::Equal, K::Semi]));
+}
+
+#[test]
+#[allow(deprecated)]
+fn start_is_the_first_set() {
+    assert_eq!(Tree::START.to_set(), Tree::FIRST);
+    assert!(parse_source::<Vec<Tree>>("{ ; { } } ;", SourcePath::Generated).is_ok());
+}
@@ -0,0 +1,125 @@
+#![cfg(feature = "trace")]
+
+use std::{
+    sync::{Arc, Mutex},
+    thread::{self, ThreadId},
+};
+
+use lasagna::{trace::Sink, *};
+
+#[derive(Token, Clone, Debug, PartialEq, Eq)]
+pub enum Tok {
+    #[token = "["]
+    Open,
+    #[token = "]"]
+    Close,
+    #[token = "x"]
+    X,
+}
+
+#[derive(Parse, Debug)]
+pub enum Nest {
+    Group(Group),
+    X(X),
+}
+
+#[derive(Parse, Debug)]
+pub struct Group {
+    pub open: Open,
+    pub inner: Vec<Nest>,
+    pub close: Close,
+}
+
+/// Panics when parsed.
+#[derive(Debug)]
+pub struct Bomb;
+
+impl Parse for Bomb {
+    type Token = Tok;
+
+    const FIRST: ParseFirst<Self::Token> = TokenSet::FULL;
+
+    fn parse(_: &mut impl Parser<Self::Token>) -> Result<Self, Error> {
+        panic!("boom")
+    }
+}
+
+#[derive(Parse, Debug)]
+pub struct Armed {
+    pub x: X,
+    pub bomb: Bomb,
+}
+
+/// Collects the events of every thread with their depth.
+#[derive(Clone, Default)]
+struct Collect(Arc<Mutex<Vec<(ThreadId, usize, String)>>>);
+
+impl Sink for Collect {
+    fn event(&self, event: &trace::Event<'_>) {
+        let id = thread::current().id();
+
+        self.0
+            .lock()
+            .unwrap()
+            .push((id, event.depth, event.to_string()));
+    }
+}
+
+/// Serializes the tests, the sink is global.
+static SINK: Mutex<()> = Mutex::new(());
+
+fn render(source: &str) -> String {
+    match parse_source::<Nest>(source, SourcePath::Generated) {
+        Ok(node) => dump_ast(&node),
+        Err(err) => err.to_string(),
+    }
+}
+
+#[test]
+fn tracing_does_not_change_parsing() {
+    let sources = ["[x [x] x]", "[x [x x]", "[x ] ]", "  [\n x"];
+    let untraced = sources.iter().map(|s| render(s)).collect::<Vec<_>>();
+
+    let _lock = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    let sink = Collect::default();
+    trace::set_sink(sink.clone());
+    let traced = sources.iter().map(|s| render(s)).collect::<Vec<_>>();
+    trace::clear_sink();
+
+    assert_eq!(traced, untraced);
+
+    let events = sink.0.lock().unwrap();
+    assert!(events
+        .iter()
+        .any(|(_, _, event)| event.ends_with(", next \"[x [x] x]\"")));
+    assert!(events
+        .iter()
+        .any(|(_, _, event)| event.ends_with(", next \"[\"")));
+}
+
+#[test]
+fn depth_is_restored_after_a_panic() {
+    let _lock = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    let sink = Collect::default();
+    trace::set_sink(sink.clone());
+
+    let panicked = std::panic::catch_unwind(|| {
+        let _ = parse_source::<Armed>("x", SourcePath::Generated);
+    })
+    .is_err();
+    let _ = parse_source::<Nest>("x", SourcePath::Generated);
+
+    trace::clear_sink();
+
+    assert!(panicked);
+
+    let id = thread::current().id();
+    let events = sink.0.lock().unwrap();
+    let depths = events
+        .iter()
+        .filter(|(thread, _, event)| *thread == id && event.starts_with("> "))
+        .map(|(_, depth, _)| *depth)
+        .collect::<Vec<_>>();
+
+    assert_eq!(depths, [0, 0]);
+}